@@ -1,15 +1,26 @@
 import java.math.BigInteger;
 import java.nio.file.Path;
+import java.util.HexFormat;
+import java.util.List;
 
 import org.assertj.core.api.Assertions;
 
+import com.fasterxml.jackson.databind.JsonNode;
+
 import com.partisiablockchain.BlockchainAddress;
 import com.partisiablockchain.language.abicodegen.Lottery;
 import com.partisiablockchain.language.abicodegen.Lottery.AccountCreationSecret;
+import com.partisiablockchain.language.abicodegen.Lottery.AccountKeyRotationSecret;
+import com.partisiablockchain.language.abicodegen.Lottery.AccountMigrationSecret;
+import com.partisiablockchain.language.abicodegen.Lottery.Allowlist;
+import com.partisiablockchain.language.abicodegen.Lottery.DiscountTier;
 import com.partisiablockchain.language.abicodegen.Lottery.LotteryCreationSecret;
+import com.partisiablockchain.language.abicodegen.Lottery.LotteryOptions;
 import com.partisiablockchain.language.abicodegen.Lottery.LotteryState;
 import com.partisiablockchain.language.abicodegen.Lottery.LotteryTicketPurchaseSecret;
+import com.partisiablockchain.language.abicodegen.Lottery.PrizeClaimSecret;
 import com.partisiablockchain.language.abicodegen.Lottery.SecretVarId;
+import com.partisiablockchain.language.abicodegen.Lottery.VoucherSecret;
 import com.partisiablockchain.language.abicodegen.Testtoken;
 import com.partisiablockchain.language.abicodegen.Testtoken.TokenState;
 import com.partisiablockchain.language.codegenlib.SecretInput;
@@ -40,6 +51,8 @@ final class LotteryTest extends JunitContractTest {
         private static final BigInteger PLAYER_INITIAL_BALANCE = toBigInteger(1000);
         private static final BigInteger LOTTERY_ENTRY_COST = toBigInteger(100);
         private static final BigInteger VALID_LOTTERY_ID = BigInteger.valueOf(123123123L); // Example lottery ID
+        private static final BigInteger ANONYMOUS_LOTTERY_ID = BigInteger.valueOf(135135135L); // Lottery with an anonymous winner
+        private static final BigInteger CLAIM_SECRET = BigInteger.valueOf(246813579L); // Claim secret of the anonymous winner
         private static final long LOTTERY_DURATION_MS = 10 * 60 * 1000; // 10 minutes
 
         // Accounts
@@ -67,13 +80,17 @@ final class LotteryTest extends JunitContractTest {
         void testCreateSecretAccount() {
                 // Create a secret account for the creator
 
-                BigInteger accountKey = BigInteger.valueOf(716473264414L);
-                createSecretAccount(accountKey, creator);
+                BigInteger randomness = BigInteger.valueOf(716473264414L);
+                createSecretAccount(randomness, creator);
 
-                assertSecretVariablesAmount(1);
+                // Balance and account key
+                assertSecretVariablesAmount(2);
                 assertSecretVariableOwner(2, creator);
+                assertSecretVariableOwner(4, creator);
 
-                assertSecretBalance(creator, BigInteger.ZERO); // Initial balance is zero
+                // Account key is derived in MPC and returned to the owner
+                Assertions.assertThat(accountKey(creator)).isNotEqualTo(randomness);
+                assertSecretBalance(creator, BigInteger.ZERO, accountKey(creator)); // Initial balance is zero
 
                 // Verify the secret account creation
                 // assertNumberOfLotteries(0); // No lottery created yet
@@ -86,8 +103,8 @@ final class LotteryTest extends JunitContractTest {
 
                 approveTokens(player1, lottery, credits);
 
-                BigInteger accountKey = BigInteger.valueOf(716473264415L);
-                createSecretAccount(accountKey, player1);
+                BigInteger randomness = BigInteger.valueOf(716473264415L);
+                createSecretAccount(randomness, player1);
 
                 purchaseCredits(player1, credits);
 
@@ -95,6 +112,224 @@ final class LotteryTest extends JunitContractTest {
                 assertSecretBalance(player1, credits);
         }
 
+        @ContractTest(previous = "testPurchaseCredits")
+        void testOwnershipPolicy() {
+                // Balances are owned by the API address by default
+                assertBalanceOwner(player1, api);
+
+                // Only the admin can switch the policy
+                Assertions.assertThatThrownBy(() -> {
+                        setOwnershipPolicy(player1, new Lottery.OwnershipPolicyRealOwner());
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Only the admin can set the ownership policy");
+
+                // Only account holders can opt in
+                Assertions.assertThatThrownBy(() -> {
+                        setRealOwnership(player3, true);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot set the ownership of an account that does not exist");
+
+                // Opt-ins only take effect under the per-user policy
+                setRealOwnership(player1, true);
+                assertBalanceOwner(player1, api);
+
+                setOwnershipPolicy(deployer, new Lottery.OwnershipPolicyPerUser());
+                assertBalanceOwner(player1, player1);
+                assertBalanceOwner(creator, api);
+
+                setRealOwnership(player1, false);
+                assertBalanceOwner(player1, api);
+
+                setOwnershipPolicy(deployer, new Lottery.OwnershipPolicyRealOwner());
+                assertBalanceOwner(player1, player1);
+                assertBalanceOwner(creator, creator);
+
+                // Balances are unaffected by their owner
+                assertSecretBalance(player1, toBigInteger(1000));
+        }
+
+        @ContractTest(previous = "testPurchaseCredits")
+        void testAdminSettings() {
+                // Only the admin can change the settings
+                Assertions.assertThatThrownBy(() -> {
+                        setApi(player1, player1);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Only the admin can set the API address");
+
+                // Rotating the API address re-owns the balances it owned
+                setApi(deployer, player3);
+                Assertions.assertThat(getLotteryContractState().api()).isEqualTo(player3);
+                assertBalanceOwner(player1, player3);
+                assertBalanceOwner(creator, player3);
+
+                // Token cannot change while the contract holds tokens backing balances
+                Assertions.assertThatThrownBy(() -> {
+                        setToken(deployer, player3);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot set the token while accounts exist");
+
+                // Previous admin loses the role
+                transferAdmin(deployer, player2);
+                Assertions.assertThat(getLotteryContractState().admin()).isEqualTo(player2);
+                Assertions.assertThatThrownBy(() -> {
+                        transferAdmin(deployer, deployer);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Only the admin can transfer the admin role");
+                setApi(player2, api);
+                assertBalanceOwner(player1, api);
+
+                Assertions.assertThat(getLotteryContractState().journal().entries())
+                                .extracting(entry -> entry.event().discriminant())
+                                .containsSubsequence(
+                                                Lottery.JournalEventD.API_ROTATED,
+                                                Lottery.JournalEventD.ADMIN_TRANSFERRED,
+                                                Lottery.JournalEventD.API_ROTATED);
+        }
+
+        @ContractTest(previous = "testPurchaseCredits")
+        void testPauseContract() {
+                Assertions.assertThatThrownBy(() -> {
+                        setPauseFlags(player1, new Lottery.PauseFlags(true, true, true, true));
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Only the admin can pause the contract");
+
+                setPauseFlags(deployer, new Lottery.PauseFlags(true, true, true, true));
+
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseCredits(player1, toBigInteger(1));
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Deposits are paused");
+                Assertions.assertThatThrownBy(() -> {
+                        createSecretAccount(entropy(), player2);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Account creation is paused");
+                Assertions.assertThatThrownBy(() -> {
+                        createLottery(
+                                        player1,
+                                        VALID_LOTTERY_ID, // Lottery account key
+                                        accountKey(player1), // Creator account key
+                                        entropy(), // Random seed
+                                        System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
+                                        LOTTERY_ENTRY_COST, // Entry cost
+                                        toBigInteger(1) // Prize pool
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Lottery creation is paused");
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(player1, VALID_LOTTERY_ID, accountKey(player1), entropy(), BigInteger.ONE);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Ticket purchases are paused");
+                Assertions.assertThatThrownBy(() -> {
+                        drawLottery(player1, VALID_LOTTERY_ID);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Draws are paused");
+
+                // Users can always exit
+                redeemCredits(player1, toBigInteger(500));
+                assertSecretBalance(player1, toBigInteger(500), accountKey(player1));
+                Assertions.assertThat(balance(player1)).isEqualTo(PLAYER_INITIAL_BALANCE.subtract(toBigInteger(500)));
+
+                // Resuming reopens the entry points
+                setPauseFlags(deployer, new Lottery.PauseFlags(false, false, false, false));
+                createSecretAccount(entropy(), player2);
+                assertSecretBalance(player2, BigInteger.ZERO, accountKey(player2));
+                Assertions.assertThat(getLotteryContractState().journal().entries())
+                                .extracting(entry -> entry.event().discriminant())
+                                .containsSubsequence(
+                                                Lottery.JournalEventD.PAUSE_FLAGS_CHANGED,
+                                                Lottery.JournalEventD.PAUSE_FLAGS_CHANGED);
+        }
+
+        @ContractTest(previous = "setupEnvironment")
+        void testSetToken() {
+                Assertions.assertThatThrownBy(() -> {
+                        setToken(player1, player3);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Only the admin can set the token");
+
+                setToken(deployer, player3);
+
+                Assertions.assertThat(getLotteryContractState().token()).isEqualTo(player3);
+                Assertions.assertThat(getLotteryContractState().journal().entries())
+                                .extracting(entry -> entry.event().discriminant())
+                                .contains(Lottery.JournalEventD.TOKEN_UPDATED);
+        }
+
+        @ContractTest(previous = "testPurchaseCredits")
+        void testRotateAccountKey() {
+                BigInteger balance = toBigInteger(1000);
+                BigInteger oldAccountKey = accountKey(player1);
+                Lottery.SecretVarId oldAccountKeyId = getLotteryContractState().accountKeys().get(player1);
+
+                // Rotation requires the old account key
+                Assertions.assertThatThrownBy(() -> {
+                        rotateAccountKey(player1, accountKey(creator), entropy());
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not rotate account key");
+                Assertions.assertThat(accountKey(player1)).isEqualTo(oldAccountKey);
+                assertSecretBalance(player1, balance, oldAccountKey);
+
+                rotateAccountKey(player1, oldAccountKey, entropy());
+
+                // Balance moves to the new account key, and the old key is deleted
+                BigInteger newAccountKey = accountKey(player1);
+                Assertions.assertThat(newAccountKey).isNotEqualTo(oldAccountKey);
+                Assertions.assertThat(getLotteryContractState().accountKeys().get(player1))
+                                .isNotEqualTo(oldAccountKeyId);
+                assertSecretBalance(player1, balance, newAccountKey);
+        }
+
+        @ContractTest(previous = "testPurchaseCredits")
+        void testRevealMyBalance() {
+                revealMyBalance(player1);
+
+                Lottery.SecretVarId revealId = getLotteryContractState().balanceReveals().get(player1);
+                Assertions.assertThat(revealId).isNotNull();
+
+                AccountBalance revealed = deserializeAccountBalance(
+                                zkNodes.getSecretVariable(lottery, revealId.rawId()));
+                Assertions.assertThat(revealed.accountKey()).isEqualTo(accountKey(player1));
+                Assertions.assertThat(revealed.balance()).isEqualTo(toBigInteger(1000));
+
+                // Revealing again replaces the outdated copy
+                revealMyBalance(player1);
+
+                Lottery.SecretVarId newRevealId = getLotteryContractState().balanceReveals().get(player1);
+                Assertions.assertThat(newRevealId).isNotEqualTo(revealId);
+                Assertions.assertThat(getLotteryContractState().balanceReveals().size()).isEqualTo(1);
+
+                // Only account holders have a balance to reveal
+                Assertions.assertThatThrownBy(() -> {
+                        revealMyBalance(player3);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot reveal the balance of an account that does not exist");
+        }
+
+        @ContractTest(previous = "testCreateSecretAccount")
+        void testCreateAccountWithDeposit() {
+                BigInteger deposit = toBigInteger(500);
+                BigInteger randomness = BigInteger.valueOf(716473264416L);
+
+                createSecretAccountWithDeposit(randomness, player2, deposit);
+
+                // Account is created with the deposit as its initial balance
+                assertSecretBalance(player2, deposit, accountKey(player2));
+                Assertions.assertThat(balance(player2)).isEqualTo(PLAYER_INITIAL_BALANCE.subtract(deposit));
+        }
+
+        @ContractTest(previous = "testCreateSecretAccount")
+        void testCreateAccountWithDepositReusedRandomness() {
+                BigInteger deposit = toBigInteger(500);
+
+                // Same randomness as the creator
+                createSecretAccountWithDeposit(BigInteger.valueOf(716473264414L), player2, deposit);
+
+                // Account keys never collide, so the account is created with a key of its own
+                Assertions.assertThat(accountKey(player2)).isNotEqualTo(accountKey(creator));
+                assertSecretBalance(player2, deposit, accountKey(player2));
+                Assertions.assertThat(balance(player2)).isEqualTo(PLAYER_INITIAL_BALANCE.subtract(deposit));
+        }
+
         @ContractTest(previous = "testCreateSecretAccount")
         void testPurchaseCreditsWithoutAccount() {
                 // Attempt to purchase credits without creating a secret account
@@ -147,22 +382,26 @@ final class LotteryTest extends JunitContractTest {
         void testRedeemCredits() {
                 BigInteger credits = toBigInteger(500);
                 // Assertions.assertThat(getLotteryContractState().accounts().get(player1)).isNull();
-                assertSecretBalance(player1, toBigInteger(1000), BigInteger.valueOf(716473264415L)); // After redeeming,
+                assertSecretBalance(player1, toBigInteger(1000), accountKey(player1)); // After redeeming,
                                                                                                      // balance should
                                                                                                      // be zero
 
                 redeemCredits(player1, credits);
 
                 // Assert the secret balance of the creator (1000 balance - 500 redeemed)
-                assertSecretBalance(player1, toBigInteger(500), BigInteger.valueOf(716473264415L)); // After redeeming,
+                assertSecretBalance(player1, toBigInteger(500), accountKey(player1)); // After redeeming,
                                                                                                     // balance should be
                                                                                                     // zero
         }
 
         @ContractTest(previous = "testPurchaseCredits")
         void testCreateLottery() {
+                // The creator funds the prize pool with credits of their own, and players purchase the tickets
+                transferTokens(deployer, creator, PLAYER_INITIAL_BALANCE);
+                approveTokens(creator, lottery, PLAYER_INITIAL_BALANCE);
+                purchaseCredits(creator, PLAYER_INITIAL_BALANCE);
                 assertSecretBalance(
-                                player1,
+                                creator,
                                 toBigInteger(1000));
 
                 BigInteger lotteryId = VALID_LOTTERY_ID; // Lottery account key
@@ -175,9 +414,9 @@ final class LotteryTest extends JunitContractTest {
                 BigInteger initialEntropy = entropy(); // Initial entropy for the lottery
 
                 createLottery(
-                                player1,
+                                creator,
                                 lotteryId, // Lottery account key
-                                BigInteger.valueOf(716473264415L), // Creator account key
+                                accountKey(creator), // Creator account key
                                 initialEntropy,
                                 deadline, // Deadline
                                 LOTTERY_ENTRY_COST, // Entry cost
@@ -187,7 +426,7 @@ final class LotteryTest extends JunitContractTest {
                 // Assert the lottery state after creation
                 LotteryState lotteryState = getLotteryState(lotteryId);
                 Assertions.assertThat(lotteryState).isNotNull();
-                Assertions.assertThat(lotteryState.creator()).isEqualTo(player1);
+                Assertions.assertThat(lotteryState.creator()).isEqualTo(creator);
                 Assertions.assertThat(lotteryState.deadline()).isEqualTo(deadline);
                 Assertions.assertThat(lotteryState.entryCost()).isEqualTo(LOTTERY_ENTRY_COST);
                 Assertions.assertThat(lotteryState.prizePool()).isEqualTo(prizePool);
@@ -201,7 +440,8 @@ final class LotteryTest extends JunitContractTest {
                 Assertions.assertThat(secretState.entropy()).isEqualTo(initialEntropy);
                 Assertions.assertThat(secretState.tickets()).isEqualTo(BigInteger.ZERO); // No tickets purchased yet
 
-                assertSecretBalance(player1, toBigInteger(1000).subtract(prizePool));
+                assertSecretBalance(creator, toBigInteger(1000).subtract(prizePool));
+                assertSecretBalance(player1, toBigInteger(1000));
                 assertLotterySecretBalance(
                                 lotteryId, // Lottery account key
                                 prizePool, // Balance after creating the lottery
@@ -213,7 +453,7 @@ final class LotteryTest extends JunitContractTest {
         void testCreateLotteryWithConflictingKey() {
                 assertSecretBalance(
                                 player1,
-                                toBigInteger(999));
+                                toBigInteger(1000));
 
                 BigInteger lotteryId = VALID_LOTTERY_ID; // Lottery account key
                 BigInteger prizePool = toBigInteger(1); // Prize pool
@@ -222,7 +462,7 @@ final class LotteryTest extends JunitContractTest {
                         createLottery(
                                         player1,
                                         lotteryId, // Lottery account key
-                                        BigInteger.valueOf(716473264415L), // Creator account key
+                                        accountKey(player1), // Creator account key
                                         entropy(), // Random seed
                                         System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
                                         LOTTERY_ENTRY_COST, // Entry cost
@@ -233,6 +473,56 @@ final class LotteryTest extends JunitContractTest {
 
         }
 
+        @ContractTest(previous = "testCreateLottery")
+        void testCreateLotteryWithAccountKey() {
+                BigInteger userKeyTag = BigInteger.ONE.shiftLeft(127);
+                BigInteger unknownAccountKey = userKeyTag.add(BigInteger.valueOf(999L));
+
+                // User account keys are tagged, and lottery IDs must stay below the tag
+                Assertions.assertThat(accountKey(creator).testBit(127)).isTrue();
+                Assertions.assertThat(accountKey(player1).testBit(127)).isTrue();
+
+                // Existing and unknown account keys are rejected alike, before any computation
+                for (BigInteger lotteryId : List.of(accountKey(creator), unknownAccountKey)) {
+                        Assertions.assertThatThrownBy(() -> {
+                                createLottery(
+                                                player1,
+                                                lotteryId, // Account key as lottery account key
+                                                accountKey(player1), // Creator account key
+                                                entropy(), // Random seed
+                                                System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
+                                                LOTTERY_ENTRY_COST, // Entry cost
+                                                toBigInteger(1) // Prize pool
+                                );
+                        }).isInstanceOf(RuntimeException.class)
+                                        .hasMessageContaining("Lottery ID must be positive and below");
+                }
+
+                // The secret lottery account key must match the public lottery ID, whether the secret key
+                // exists or not
+                for (BigInteger secretLotteryKey : List.of(accountKey(creator), unknownAccountKey)) {
+                        SecretInput input = Lottery.createLottery(
+                                        BigInteger.valueOf(246246246L),
+                                        System.currentTimeMillis() + LOTTERY_DURATION_MS,
+                                        LOTTERY_ENTRY_COST,
+                                        toBigInteger(1),
+                                        defaultOptions().build())
+                                        .secretInput(new LotteryCreationSecret(
+                                                        secretLotteryKey,
+                                                        accountKey(player1),
+                                                        entropy(),
+                                                        BigInteger.ZERO));
+
+                        Assertions.assertThatThrownBy(() -> {
+                                blockchain.sendSecretInput(lottery, player1, input.secretInput(), input.publicRpc());
+                        }).isInstanceOf(RuntimeException.class)
+                                        .hasMessageContaining("Could not create lottery");
+                }
+
+                // Nothing has been charged
+                assertSecretBalance(player1, toBigInteger(1000), accountKey(player1));
+        }
+
         @ContractTest(previous = "testPurchaseCredits")
         void testCreateLotteryWithInsufficientBalance() {
                 // Confirm player1 has expected balance before creating lottery
@@ -248,7 +538,7 @@ final class LotteryTest extends JunitContractTest {
                         createLottery(
                                         player1,
                                         lotteryId, // Lottery account key
-                                        BigInteger.valueOf(716473264415L), // Creator account key
+                                        accountKey(player1), // Creator account key
                                         entropy(), // Random seed
                                         System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
                                         LOTTERY_ENTRY_COST, // Entry cost
@@ -263,7 +553,7 @@ final class LotteryTest extends JunitContractTest {
                 // Confirm player1 has expected balance before purchasing tickets
                 assertSecretBalance(
                                 player1,
-                                toBigInteger(999));
+                                toBigInteger(1000));
 
                 BigInteger lotteryId = VALID_LOTTERY_ID; // Lottery account key
                 BigInteger ticketCount = BigInteger.valueOf(5); // Number of tickets to purchase
@@ -278,7 +568,7 @@ final class LotteryTest extends JunitContractTest {
                 purchaseTickets(
                                 player1,
                                 lotteryId,
-                                BigInteger.valueOf(716473264415L), // Player account key
+                                accountKey(player1), // Player account key
                                 entropy, // Random seed
                                 ticketCount // Number of tickets to purchase
                 );
@@ -294,8 +584,8 @@ final class LotteryTest extends JunitContractTest {
                 // Assert the secret balance of the player after purchasing tickets
                 assertSecretBalance(
                                 player1,
-                                toBigInteger(999).subtract(LOTTERY_ENTRY_COST.multiply(ticketCount)),
-                                BigInteger.valueOf(716473264415L) // Player account key
+                                toBigInteger(1000).subtract(LOTTERY_ENTRY_COST.multiply(ticketCount)),
+                                accountKey(player1) // Player account key
                 );
                 // Assert the lottery secret balance after purchasing tickets
                 assertLotterySecretBalance(
@@ -306,168 +596,1168 @@ final class LotteryTest extends JunitContractTest {
                 );
         }
 
-        @ContractTest(previous = "testPurchaseTickets")
-        void testPurchaseTicketsWithoutAccount() {
-                // Attempt to purchase tickets without creating a secret account
-                BigInteger lotteryId = VALID_LOTTERY_ID; // Lottery account key
-                BigInteger ticketCount = BigInteger.valueOf(3); // Number of tickets to purchase
+        @ContractTest(previous = "testCreateLottery")
+        void testAccountKeysAreNotPublic() {
+                BigInteger accountKey = accountKey(player1);
+
+                // Account keys only live in secret variables, never in the public state
+                String publicState = blockchain.getContractStateJson(lottery).getNode("").toString();
+                Assertions.assertThat(publicState)
+                                .doesNotContain(accountKey.toString())
+                                .doesNotContain(accountKey.toString(16));
+
+                // Nor in the public part of a ticket purchase
+                SecretInput input = Lottery.purchaseTickets(VALID_LOTTERY_ID).secretInput(
+                                new LotteryTicketPurchaseSecret(
+                                                VALID_LOTTERY_ID,
+                                                accountKey,
+                                                BigInteger.ONE,
+                                                entropy(),
+                                                BigInteger.ZERO,
+                                                BigInteger.ZERO,
+                                                BigInteger.ZERO,
+                                                BigInteger.ZERO));
+                Assertions.assertThat(HexFormat.of().formatHex(input.publicRpc()))
+                                .doesNotContain(accountKey.toString(16));
+
+                // The winner is resolved from the owner of the ticket purchase instead
+                blockchain.sendSecretInput(lottery, player1, input.secretInput(), input.publicRpc());
+                blockchain.waitForBlockProductionTime(System.currentTimeMillis() + LOTTERY_DURATION_MS + 1000);
+                drawLottery(creator, VALID_LOTTERY_ID);
+                Assertions.assertThat(getLotteryState(VALID_LOTTERY_ID).winner()).isEqualTo(player1);
+        }
 
-                Assertions.assertThatThrownBy(() -> {
-                        purchaseTickets(
-                                        player3,
-                                        lotteryId,
-                                        BigInteger.valueOf(716473264415L), // Player account key
-                                        entropy(), // Random seed
-                                        ticketCount // Number of tickets to purchase
-                        );
-                }).isInstanceOf(RuntimeException.class)
-                                .hasMessageContaining("Cannot purchase lottery tickets");
+        @ContractTest(previous = "testCreateLottery")
+        void testPurchaseTicketsWithTokens() {
+                BigInteger lotteryId = VALID_LOTTERY_ID;
+                BigInteger ticketCount = BigInteger.valueOf(2);
+
+                createSecretAccount(BigInteger.valueOf(716473264416L), player2);
+                BigInteger accountKey = accountKey(player2);
+
+                purchaseTicketsWithTokens(player2, lotteryId, accountKey, entropy(), ticketCount, ticketCount);
+
+                // Tokens are taken directly from the wallet, leaving the credit balance untouched
+                BigInteger cost = LOTTERY_ENTRY_COST.multiply(ticketCount);
+                Assertions.assertThat(balance(player2)).isEqualTo(PLAYER_INITIAL_BALANCE.subtract(cost));
+                assertSecretBalance(player2, BigInteger.ZERO, accountKey);
+
+                assertLotterySecretBalance(lotteryId, toBigInteger(1).add(cost), lotteryId);
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(ticketCount);
         }
 
-        @ContractTest(previous = "testPurchaseTickets")
-        void testPurchaseTicketsWithInsufficientBalance() {
-                // Attempt to purchase tickets with insufficient balance
-                BigInteger lotteryId = VALID_LOTTERY_ID; // Lottery account key
-                BigInteger ticketCount = BigInteger.valueOf(100000000); // Number of tickets to purchase
+        @ContractTest(previous = "testCreateLottery")
+        void testPurchaseTicketsWithTokensMismatchedTickets() {
+                BigInteger lotteryId = VALID_LOTTERY_ID;
+                createSecretAccount(BigInteger.valueOf(716473264416L), player2);
+                BigInteger accountKey = accountKey(player2);
 
                 Assertions.assertThatThrownBy(() -> {
-                        purchaseTickets(
-                                        player1,
-                                        lotteryId,
-                                        BigInteger.valueOf(716473264415L), // Player account key
-                                        entropy(), // Random seed
-                                        ticketCount // Number of tickets to purchase
-                        );
+                        // Secret ticket count does not match the tickets paid for
+                        purchaseTicketsWithTokens(player2, lotteryId, accountKey, entropy(), BigInteger.ONE,
+                                        BigInteger.TEN);
                 }).isInstanceOf(RuntimeException.class)
                                 .hasMessageContaining("Could not purchase lottery ticket");
-        }
 
-        @ContractTest(previous = "testPurchaseTickets")
-        void testDrawLotteryBeforeDeadline() {
-                Assertions.assertThatThrownBy(() -> {
-                        drawLottery(player1, VALID_LOTTERY_ID); // Attempt to draw before deadline
-                }).isInstanceOf(RuntimeException.class)
-                                .hasMessageContaining("Cannot draw a winner before the lottery deadline!");
+                // Payment is refunded and no tickets are recorded
+                Assertions.assertThat(balance(player2)).isEqualTo(PLAYER_INITIAL_BALANCE);
+                assertLotterySecretBalance(lotteryId, toBigInteger(1), lotteryId);
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.ZERO);
         }
 
-        @ContractTest(previous = "testDrawLotteryBeforeDeadline")
-        void testDrawLotteryAfterDeadline() {
-                // Set the lottery deadline to the past
-                lotteryDeadline = System.currentTimeMillis() + LOTTERY_DURATION_MS + 1000; // 1 second in the past
+        @ContractTest(previous = "testCreateLottery")
+        void testPurchaseTicketsWithReferral() {
+                BigInteger lotteryId = BigInteger.valueOf(321321321L);
+                BigInteger prizePool = toBigInteger(1);
+                BigInteger ticketCount = BigInteger.valueOf(2);
+                BigInteger spend = LOTTERY_ENTRY_COST.multiply(ticketCount);
+                BigInteger reward = spend.divide(BigInteger.TEN); // 10% referral reward
 
-                blockchain.waitForBlockProductionTime(lotteryDeadline);
+                createLottery(
+                                creator,
+                                lotteryId, // Lottery account key
+                                accountKey(creator), // Creator account key
+                                entropy(), // Random seed
+                                System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
+                                LOTTERY_ENTRY_COST, // Entry cost
+                                prizePool, // Prize pool
+                                defaultOptions().withReferralReward(10).build()
+                );
 
-                // Check lottery balance is expected amount
-                assertLotterySecretBalance(VALID_LOTTERY_ID, toBigInteger(501));
+                // Referred by player2, using the account key of player2
+                createSecretAccount(BigInteger.valueOf(716473264416L), player2);
+                purchaseTickets(
+                                player1,
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                ticketCount,
+                                accountKey(player2) // Referrer account key
+                );
 
-                // tmp debugging
-                // String s =
-                // blockchain.getContractStateJson(lottery).getNode("").toPrettyString();
-                // Assertions.assertThat(s).isNull();
-                CompactBitArray ticket = zkNodes.getSecretVariable(lottery, 16);
+                assertSecretBalance(player2, reward, accountKey(player2));
+                assertLotterySecretBalance(lotteryId, prizePool.add(spend).subtract(reward), lotteryId);
 
-                // Assertions.assertThat(ticket).isNull();
-                // Decode the ticket
-                BitInput stream = BitInput.create(ticket.data());
-                BigInteger lotteryAccountKey = stream.readUnsignedBigInteger(128);
-                BigInteger playerAccountKey = stream.readUnsignedBigInteger(128);
-                BigInteger ticketEntropy = stream.readUnsignedBigInteger(128);
-                BigInteger ticketCount = stream.readUnsignedBigInteger(128);
-                Assertions.assertThat(lotteryAccountKey).isEqualTo(VALID_LOTTERY_ID);
-                Assertions.assertThat(playerAccountKey).isEqualTo(BigInteger.valueOf(716473264415L));
-                Assertions.assertThat(ticketEntropy).isGreaterThan(BigInteger.ZERO);
-                Assertions.assertThat(ticketCount).isGreaterThan(BigInteger.ZERO);
+                // Unknown referrer key, own key and lottery key, so the lottery receives the full spend
+                for (BigInteger referrerAccountKey : List.of(
+                                BigInteger.valueOf(999L),
+                                accountKey(player1),
+                                VALID_LOTTERY_ID)) {
+                        purchaseTickets(
+                                        player1,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        ticketCount,
+                                        referrerAccountKey);
+                }
 
-                // Pre-draw secret state assertions
-                SecretLotteryState preSecretState = getSecretLotteryState(VALID_LOTTERY_ID);
-                Assertions.assertThat(preSecretState).isNotNull();
-                Assertions.assertThat(preSecretState.tickets()).isGreaterThan(BigInteger.ZERO); // Tickets should be
-                                                                                                // purchased
-                Assertions.assertThat(preSecretState.entropy()).isNotNull(); // Entropy should be set
+                assertSecretBalance(player2, reward, accountKey(player2));
+                assertLotterySecretBalance(
+                                lotteryId,
+                                prizePool.add(spend.multiply(BigInteger.valueOf(4))).subtract(reward),
+                                lotteryId);
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets())
+                                .isEqualTo(ticketCount.multiply(BigInteger.valueOf(4)));
+        }
 
-                // Draw the lottery winner
-                TxExecution execution = drawLottery(player1, VALID_LOTTERY_ID);
+        @ContractTest(previous = "testPurchaseTicketsWithReferral")
+        void testReferralAfterAccountKeyRotation() {
+                BigInteger lotteryId = BigInteger.valueOf(321321321L);
+                BigInteger reward = LOTTERY_ENTRY_COST.multiply(BigInteger.TWO).divide(BigInteger.TEN);
+                BigInteger oldAccountKey = accountKey(player2);
 
-                // Assert the lottery state after drawing
-                LotteryState lotteryState = getLotteryState(VALID_LOTTERY_ID);
-                Assertions.assertThat(lotteryState.status().discriminant()).isEqualTo(Lottery.LotteryStatusD.DRAWN);
+                purchaseTickets(
+                                player1,
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                BigInteger.TWO, // Number of tickets to purchase
+                                oldAccountKey // Referrer account key
+                );
 
-                // Assert the secret state of the lottery
-                SecretLotteryState secretState = getSecretLotteryState(VALID_LOTTERY_ID);
-                Assertions.assertThat(secretState).isNotNull();
-                Assertions.assertThat(secretState.tickets()).isGreaterThan(BigInteger.ZERO); // Tickets should be
-                                                                                             // purchased
+                // The credited balance is kept by the rotation
+                rotateAccountKey(player2, oldAccountKey, entropy());
+                assertSecretBalance(player2, reward.multiply(BigInteger.TWO), accountKey(player2));
 
-                // Assert that the winner is drawn and the prize pool is distributed
-                // Note: The actual winner logic is not implemented in this test, so we just
-                // check that the action executed successfully
-                Assertions.assertThat(execution.isSuccess()).isTrue();
+                // The old key no longer refers, the new key does
+                for (BigInteger referrerAccountKey : List.of(oldAccountKey, accountKey(player2))) {
+                        purchaseTickets(
+                                        player1,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        BigInteger.TWO, // Number of tickets to purchase
+                                        referrerAccountKey);
+                }
+                assertSecretBalance(player2, reward.multiply(BigInteger.valueOf(3)), accountKey(player2));
+        }
 
-                // Check lottery balance is expected amount
-                // BigInteger ticketsBought = preSecretState.tickets();
-                // BigInteger expectedCreatorBalance =
-                // lotteryState.entryCost().multiply(ticketsBought).subtract();
-                assertLotterySecretBalance(VALID_LOTTERY_ID, lotteryState.prizePool());
-                // Check the creator's balance is updated as expected
-                assertSecretBalance(
+        @ContractTest(previous = "testPurchaseTicketsWithReferral")
+        void testCloseAccountWithReferralRewards() {
+                BigInteger lotteryId = BigInteger.valueOf(321321321L);
+                BigInteger reward = LOTTERY_ENTRY_COST.multiply(BigInteger.TWO).divide(BigInteger.TEN);
+
+                purchaseTickets(
                                 player1,
-                                toBigInteger(999), // Player should receive the prize pool
-                                BigInteger.valueOf(716473264415L) // Player account key
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                BigInteger.TWO, // Number of tickets to purchase
+                                accountKey(player2) // Referrer account key
                 );
+
+                // Credited rewards are withdrawn together with the balance
+                BigInteger tokenBalance = balance(player2);
+                closeAccount(player2);
+                Assertions.assertThat(balance(player2))
+                                .isEqualTo(tokenBalance.add(reward.multiply(BigInteger.TWO)));
         }
 
-        @ContractTest(previous = "testDrawLotteryAfterDeadline")
-        void testClaimWinningPrize() {
-                // Confirm player1 has expected balance before claiming winnings
-                assertSecretBalance(
-                                player1,
-                                toBigInteger(999), // After drawing, player should have 501 in balance
-                                BigInteger.valueOf(716473264415L) // Player account key
+        @ContractTest(previous = "testCreateLottery")
+        void testPurchaseTicketsWithDiscount() {
+                BigInteger lotteryId = BigInteger.valueOf(456456456L);
+                BigInteger prizePool = toBigInteger(1);
+                BigInteger discountedPrice = toBigInteger(80); // Buy 10, pay for 8
+
+                createLottery(
+                                creator,
+                                lotteryId, // Lottery account key
+                                accountKey(creator), // Creator account key
+                                entropy(), // Random seed
+                                System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
+                                LOTTERY_ENTRY_COST, // Entry cost
+                                prizePool, // Prize pool
+                                defaultOptions()
+                                                .withDiscountTiers(new DiscountTier(BigInteger.TEN, discountedPrice))
+                                                .build()
                 );
 
-                claimWinnings(player1, VALID_LOTTERY_ID);
+                BigInteger balance = toBigInteger(1000);
+                assertSecretBalance(player1, balance, accountKey(player1));
 
-                // Assert the secret balance of the player after claiming winnings
-                assertSecretBalance(
+                // 10 tickets reach the discount tier
+                purchaseTickets(
                                 player1,
-                                toBigInteger(1000), // Player should receive the prize pool
-                                BigInteger.valueOf(716473264415L) // Player account key
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                BigInteger.TEN // Number of tickets to purchase
                 );
-        }
 
-        @ContractTest(previous = "testClaimWinningPrize")
-        void testDrawWithNoTickets() {
-                // Create a fresh lottery
-                BigInteger lotteryId = BigInteger.valueOf(999999999L); // New lottery account key
-                BigInteger prizePool = toBigInteger(100); // Prize pool
-                long deadline = System.currentTimeMillis() + (LOTTERY_DURATION_MS * 2); // Deadline (later than previous
-                                                                                        // tests since we've already
-                                                                                        // time travelled in this test
-                                                                                        // flow)
+                balance = balance.subtract(discountedPrice.multiply(BigInteger.TEN));
+                assertSecretBalance(player1, balance, accountKey(player1));
 
-                // Double check balance is as expected
-                assertSecretBalance(
+                // A single ticket is charged the entry cost
+                purchaseTickets(
                                 player1,
-                                toBigInteger(1000), // Initial balance
-                                BigInteger.valueOf(716473264415L) // Player account key
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                BigInteger.ONE // Number of tickets to purchase
                 );
 
+                balance = balance.subtract(LOTTERY_ENTRY_COST);
+                assertSecretBalance(player1, balance, accountKey(player1));
+                assertLotterySecretBalance(
+                                lotteryId,
+                                prizePool.add(discountedPrice.multiply(BigInteger.TEN)).add(LOTTERY_ENTRY_COST),
+                                lotteryId);
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.valueOf(11));
+        }
+
+        @ContractTest(previous = "testCreateLottery")
+        void testAllowlistedLottery() {
+                BigInteger lotteryId = BigInteger.valueOf(246246246L);
+
                 createLottery(
-                                player1,
+                                creator,
                                 lotteryId, // Lottery account key
-                                BigInteger.valueOf(716473264415L), // Creator account key
+                                accountKey(creator), // Creator account key
                                 entropy(), // Random seed
-                                deadline, // Deadline
+                                System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
                                 LOTTERY_ENTRY_COST, // Entry cost
-                                prizePool // Prize pool
+                                toBigInteger(1), // Prize pool
+                                defaultOptions()
+                                                .withAllowlist(new Allowlist(token, List.of(player1, player1))) // Holders of the token
+                                                .build()
+                );
+
+                // Duplicate members are dropped
+                Assertions.assertThat(getLotteryState(lotteryId).options().allowlist().members())
+                                .containsExactly(player1);
+
+                // Members can purchase tickets
+                purchaseTickets(
+                                player1,
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                BigInteger.ONE // Number of tickets to purchase
+                );
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.ONE);
+
+                // Non-members cannot
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(
+                                        player2,
+                                        lotteryId,
+                                        accountKey(player2), // Player account key
+                                        entropy(), // Random seed
+                                        BigInteger.ONE // Number of tickets to purchase
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Not on the allowlist of lottery with ID");
+
+                // Only the creator manages the allowlist
+                Assertions.assertThatThrownBy(() -> {
+                        blockchain.sendAction(player2, lottery, Lottery.addToAllowlist(lotteryId, List.of(player2)));
+                }).isInstanceOf(ActionFailureException.class)
+                                .hasMessageContaining("Only the creator of the lottery can manage its allowlist!");
+
+                blockchain.sendAction(creator, lottery, Lottery.addToAllowlist(lotteryId, List.of(player2)));
+                Assertions.assertThat(getLotteryState(lotteryId).options().allowlist().members())
+                                .containsExactly(player1, player2);
+
+                blockchain.sendAction(creator, lottery, Lottery.removeFromAllowlist(lotteryId, List.of(player1)));
+                Assertions.assertThat(getLotteryState(lotteryId).options().allowlist().members())
+                                .containsExactly(player2);
+        }
+
+        @ContractTest(previous = "testCreateLottery")
+        void testPurchaseTicketsWithAccessCode() {
+                BigInteger lotteryId = BigInteger.valueOf(789789789L);
+                BigInteger accessCode = BigInteger.valueOf(424242L);
+
+                createLottery(
+                                creator,
+                                lotteryId, // Lottery account key
+                                accountKey(creator), // Creator account key
+                                entropy(), // Random seed
+                                System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
+                                LOTTERY_ENTRY_COST, // Entry cost
+                                toBigInteger(1), // Prize pool
+                                defaultOptions().build(),
+                                accessCode);
+
+                // Wrong access code
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(
+                                        player1,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        BigInteger.ONE, // Number of tickets to purchase
+                                        BigInteger.ZERO, // No referrer account key
+                                        BigInteger.valueOf(123L) // Wrong access code
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.ZERO);
+
+                // Correct access code
+                purchaseTickets(
+                                player1,
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                BigInteger.ONE, // Number of tickets to purchase
+                                BigInteger.ZERO, // No referrer account key
+                                accessCode);
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.ONE);
+        }
+
+        @ContractTest(previous = "testCreateLottery")
+        void testRedeemVoucher() {
+                BigInteger lotteryId = BigInteger.valueOf(321321321L);
+                BigInteger prizePool = toBigInteger(1);
+                BigInteger ticketsPerVoucher = BigInteger.TWO;
+                BigInteger voucherCode = BigInteger.valueOf(777777L);
+
+                createLottery(
+                                creator,
+                                lotteryId, // Lottery account key
+                                accountKey(creator), // Creator account key
+                                entropy(), // Random seed
+                                System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
+                                LOTTERY_ENTRY_COST, // Entry cost
+                                prizePool, // Prize pool
+                                defaultOptions().withVouchers(2, ticketsPerVoucher).build()
+                );
+
+                // Creator only funds the prize pool, vouchers are free entries
+                BigInteger balance = toBigInteger(999).subtract(prizePool);
+                assertSecretBalance(creator, balance, accountKey(creator));
+                assertLotterySecretBalance(lotteryId, prizePool, lotteryId);
+
+                // Only the creator registers vouchers
+                Assertions.assertThatThrownBy(() -> {
+                        addVoucher(player2, lotteryId, voucherCode);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Only the creator of the lottery can add vouchers!");
+
+                addVoucher(creator, lotteryId, voucherCode);
+                Assertions.assertThat(getLotteryState(lotteryId).vouchersRegistered()).isEqualTo(1);
+
+                // Unknown voucher code
+                Assertions.assertThatThrownBy(() -> {
+                        redeemVoucher(
+                                        player1,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        ticketsPerVoucher,
+                                        BigInteger.valueOf(123L) // Wrong voucher code
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.ZERO);
+
+                // Valid voucher code gives free tickets
+                redeemVoucher(
+                                player1,
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                ticketsPerVoucher,
+                                voucherCode);
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(ticketsPerVoucher);
+                assertSecretBalance(player1, toBigInteger(1000), accountKey(player1));
+                assertSecretBalance(creator, balance, accountKey(creator));
+                assertLotterySecretBalance(lotteryId, prizePool, lotteryId);
+
+                // Vouchers can only be redeemed once
+                Assertions.assertThatThrownBy(() -> {
+                        redeemVoucher(
+                                        player1,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        ticketsPerVoucher,
+                                        voucherCode);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(ticketsPerVoucher);
+        }
+
+        @ContractTest(previous = "testCreateLottery")
+        void testCreatorCannotEnterOwnLottery() {
+                BigInteger lotteryId = BigInteger.valueOf(987987987L);
+
+                createLottery(
+                                player1,
+                                lotteryId, // Lottery account key
+                                accountKey(player1), // Creator account key
+                                entropy(), // Random seed
+                                System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
+                                LOTTERY_ENTRY_COST, // Entry cost
+                                toBigInteger(1), // Prize pool
+                                defaultOptions().build());
+
+                BigInteger balance = toBigInteger(999);
+
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(
+                                        player1,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        BigInteger.ONE // Number of tickets to purchase
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+
+                // Nothing has been charged
+                assertSecretBalance(player1, balance, accountKey(player1));
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.ZERO);
+        }
+
+        @ContractTest(previous = "testCreateLottery")
+        void testGiveawayLottery() {
+                BigInteger lotteryId = BigInteger.valueOf(654654654L);
+                LotteryOptions giveaway = defaultOptions().withGiveaway().build();
+
+                // Giveaways are free to enter
+                Assertions.assertThatThrownBy(() -> {
+                        createLottery(
+                                        creator,
+                                        lotteryId, // Lottery account key
+                                        accountKey(creator), // Creator account key
+                                        entropy(), // Random seed
+                                        System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
+                                        LOTTERY_ENTRY_COST, // Entry cost
+                                        toBigInteger(1), // Prize pool
+                                        giveaway);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Giveaway lotteries must be free to enter");
+
+                createLottery(
+                                creator,
+                                lotteryId, // Lottery account key
+                                accountKey(creator), // Creator account key
+                                entropy(), // Random seed
+                                System.currentTimeMillis() + LOTTERY_DURATION_MS, // Deadline
+                                BigInteger.ZERO, // Entry cost
+                                toBigInteger(1), // Prize pool
+                                giveaway);
+
+                // Single free entry
+                purchaseTickets(
+                                player1,
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                BigInteger.ONE // Number of tickets to purchase
+                );
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.ONE);
+                assertSecretBalance(player1, toBigInteger(1000), accountKey(player1));
+
+                // Second entry of the same account
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(
+                                        player1,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        BigInteger.ONE // Number of tickets to purchase
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+
+                // Entry under the account key of another account
+                createSecretAccount(entropy(), player2);
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(
+                                        player2,
+                                        lotteryId,
+                                        accountKey(player1), // Account key of player1
+                                        entropy(), // Random seed
+                                        BigInteger.ONE // Number of tickets to purchase
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.ONE);
+        }
+
+        @ContractTest(previous = "testGiveawayLottery")
+        void testRotateAccountKeyWithGiveawayEntry() {
+                BigInteger lotteryId = BigInteger.valueOf(654654654L);
+                BigInteger accountKey = accountKey(player1);
+
+                // The giveaway entry holds the account key, so a new key would allow a second entry
+                Assertions.assertThatThrownBy(() -> {
+                        rotateAccountKey(player1, accountKey, entropy());
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining(
+                                                "Cannot rotate the account key with tickets in lotteries that have not been drawn");
+                Assertions.assertThat(accountKey(player1)).isEqualTo(accountKey);
+
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(player1, lotteryId, accountKey, entropy(), BigInteger.ONE);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.ONE);
+        }
+
+        @ContractTest(previous = "testCreatorCannotEnterOwnLottery")
+        void testRotateAccountKeyAsCreator() {
+                BigInteger lotteryId = BigInteger.valueOf(987987987L);
+                BigInteger accountKey = accountKey(player1);
+
+                // The lottery holds the account key of the creator, so a new key would let them enter
+                Assertions.assertThatThrownBy(() -> {
+                        rotateAccountKey(player1, accountKey, entropy());
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot rotate the account key with lotteries that have not been drawn");
+                Assertions.assertThat(accountKey(player1)).isEqualTo(accountKey);
+
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(player1, lotteryId, accountKey, entropy(), BigInteger.ONE);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.ZERO);
+        }
+
+        @ContractTest(previous = "testCreateLottery")
+        void testDrawAnonymousWinner() {
+                BigInteger lotteryId = ANONYMOUS_LOTTERY_ID;
+                BigInteger prizePool = toBigInteger(1);
+                long deadline = System.currentTimeMillis() + LOTTERY_DURATION_MS;
+
+                createLottery(
+                                creator,
+                                lotteryId, // Lottery account key
+                                accountKey(creator), // Creator account key
+                                entropy(), // Random seed
+                                deadline, // Deadline
+                                LOTTERY_ENTRY_COST, // Entry cost
+                                prizePool, // Prize pool
+                                defaultOptions().withAnonymousWinner().build());
+
+                purchaseTickets(
+                                player1,
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                BigInteger.ONE, // Number of tickets to purchase
+                                BigInteger.ZERO, // No referrer account key
+                                BigInteger.ZERO, // No access code
+                                CLAIM_SECRET);
+
+                blockchain.waitForBlockProductionTime(deadline + 1);
+                drawLottery(creator, lotteryId);
+
+                // Drawn, but the winner is not published
+                LotteryState lotteryState = getLotteryState(lotteryId);
+                Assertions.assertThat(lotteryState.status().discriminant()).isEqualTo(Lottery.LotteryStatusD.DRAWN);
+                Assertions.assertThat(lotteryState.winner()).isNull();
+                Assertions.assertThat(lotteryState.winnerClaimId()).isNotNull();
+                Assertions.assertThat(getLotteryContractState().journal().entries())
+                                .extracting(entry -> entry.event().discriminant())
+                                .contains(Lottery.JournalEventD.LOTTERY_DRAWN_ANONYMOUSLY)
+                                .doesNotContain(Lottery.JournalEventD.LOTTERY_DRAWN);
+
+                // Remainder of the ticket sales goes to the creator
+                assertSecretBalance(creator, toBigInteger(998).add(LOTTERY_ENTRY_COST), accountKey(creator));
+                assertSecretBalance(player1, toBigInteger(1000).subtract(LOTTERY_ENTRY_COST), accountKey(player1));
+                assertLotterySecretBalance(lotteryId, prizePool, lotteryId);
+        }
+
+        @ContractTest(previous = "testDrawAnonymousWinner")
+        void testClaimToPayoutAddress() {
+                BigInteger lotteryId = ANONYMOUS_LOTTERY_ID;
+                BigInteger prizePool = toBigInteger(1);
+                BigInteger payoutBalance = balance(player3);
+
+                // Prize cannot be claimed publicly
+                Assertions.assertThatThrownBy(() -> {
+                        claimWinnings(player1, lotteryId);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("has an anonymous winner");
+
+                // Knowing the account key of the winner, like the API owning the balances does, is not
+                // enough to redirect the payout
+                for (BigInteger claimSecret : List.of(accountKey(player1), accountKey(creator), BigInteger.ZERO)) {
+                        Assertions.assertThatThrownBy(() -> {
+                                claimToPayoutAddress(api, lotteryId, api, claimSecret);
+                        }).isInstanceOf(RuntimeException.class)
+                                        .hasMessageContaining("Could not claim prize");
+                }
+                Assertions.assertThat(balance(api)).isEqualTo(BigInteger.ZERO);
+                assertLotterySecretBalance(lotteryId, prizePool, lotteryId);
+
+                // Claimed with the claim secret of the winning ticket, from an address without an account,
+                // so it is not linked to the winner
+                claimToPayoutAddress(player3, lotteryId, player3, CLAIM_SECRET);
+
+                Assertions.assertThat(balance(player3)).isEqualTo(payoutBalance.add(prizePool));
+                LotteryState lotteryState = getLotteryState(lotteryId);
+                Assertions.assertThat(lotteryState.status().discriminant()).isEqualTo(Lottery.LotteryStatusD.COMPLETE);
+                Assertions.assertThat(lotteryState.winner()).isNull();
+                Assertions.assertThat(lotteryState.winnerClaimId()).isNull();
+
+                // Prize is burned from the lottery, and the winner balance is untouched
+                assertLotterySecretBalance(lotteryId, BigInteger.ZERO, lotteryId);
+                assertSecretBalance(player1, toBigInteger(1000).subtract(LOTTERY_ENTRY_COST), accountKey(player1));
+        }
+
+        @ContractTest(previous = "testPurchaseTickets")
+        void testPurchaseTicketsWithoutAccount() {
+                // Attempt to purchase tickets without creating a secret account
+                BigInteger lotteryId = VALID_LOTTERY_ID; // Lottery account key
+                BigInteger ticketCount = BigInteger.valueOf(3); // Number of tickets to purchase
+
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(
+                                        player3,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        ticketCount // Number of tickets to purchase
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot purchase lottery tickets");
+        }
+
+        @ContractTest(previous = "testPurchaseTickets")
+        void testPurchaseTicketsWithInsufficientBalance() {
+                // Attempt to purchase tickets with insufficient balance
+                BigInteger lotteryId = VALID_LOTTERY_ID; // Lottery account key
+                BigInteger ticketCount = BigInteger.valueOf(100000000); // Number of tickets to purchase
+
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(
+                                        player1,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        ticketCount // Number of tickets to purchase
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+        }
+
+        @ContractTest(previous = "testPurchaseTickets")
+        void testPurchaseTicketsWithInvalidTicketCount() {
+                BigInteger lotteryId = VALID_LOTTERY_ID; // Lottery account key
+                BigInteger balance = toBigInteger(1000).subtract(LOTTERY_ENTRY_COST.multiply(BigInteger.valueOf(5)));
+
+                // Zero tickets
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(
+                                        player1,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        BigInteger.ZERO // Number of tickets to purchase
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+
+                // Ticket count for which the cost wraps around to a small amount
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(
+                                        player1,
+                                        lotteryId,
+                                        accountKey(player1), // Player account key
+                                        entropy(), // Random seed
+                                        BigInteger.ONE.shiftLeft(128).divide(LOTTERY_ENTRY_COST).add(BigInteger.ONE)
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+
+                assertSecretBalance(player1, balance, accountKey(player1));
+                Assertions.assertThat(getSecretLotteryState(lotteryId).tickets()).isEqualTo(BigInteger.valueOf(5));
+        }
+
+        @ContractTest(previous = "testPurchaseTickets")
+        void testRevealSalesToCreator() {
+                SecretVarId secretStateId = getLotteryState(VALID_LOTTERY_ID).secretStateId();
+
+                revealSalesToCreator(creator, VALID_LOTTERY_ID);
+
+                LotteryState lotteryState = getLotteryState(VALID_LOTTERY_ID);
+                Assertions.assertThat(lotteryState.salesReportId()).isNotNull();
+                // The canonical secret state is left untouched
+                Assertions.assertThat(lotteryState.secretStateId()).isEqualTo(secretStateId);
+
+                CompactBitArray report = zkNodes.getSecretVariable(lottery, lotteryState.salesReportId().rawId());
+                BitInput stream = BitInput.create(report.data());
+                BigInteger tickets = stream.readUnsignedBigInteger(128);
+                BigInteger balance = stream.readUnsignedBigInteger(128);
+                Assertions.assertThat(tickets).isEqualTo(BigInteger.valueOf(5));
+                Assertions.assertThat(balance).isEqualTo(toBigInteger(1).add(LOTTERY_ENTRY_COST.multiply(tickets)));
+
+                // Only the creator can reveal the sales
+                Assertions.assertThatThrownBy(() -> {
+                        revealSalesToCreator(player2, VALID_LOTTERY_ID);
+                }).isInstanceOf(ActionFailureException.class)
+                                .hasMessageContaining("Only the creator of the lottery can reveal its sales!");
+        }
+
+        @ContractTest(previous = "testPurchaseTickets")
+        void testDrawLotteryBeforeDeadline() {
+                Assertions.assertThatThrownBy(() -> {
+                        drawLottery(creator, VALID_LOTTERY_ID); // Attempt to draw before deadline
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot draw a winner before the lottery deadline!");
+        }
+
+        @ContractTest(previous = "testDrawLotteryBeforeDeadline")
+        void testDrawLotteryAfterDeadline() {
+                // Set the lottery deadline to the past
+                lotteryDeadline = System.currentTimeMillis() + LOTTERY_DURATION_MS + 1000; // 1 second in the past
+
+                blockchain.waitForBlockProductionTime(lotteryDeadline);
+
+                // Check lottery balance is expected amount
+                assertLotterySecretBalance(VALID_LOTTERY_ID, toBigInteger(501));
+
+                // tmp debugging
+                // String s =
+                // blockchain.getContractStateJson(lottery).getNode("").toPrettyString();
+                // Assertions.assertThat(s).isNull();
+                CompactBitArray ticket = zkNodes.getSecretVariable(lottery, 21);
+
+                // Assertions.assertThat(ticket).isNull();
+                // Decode the ticket
+                BitInput stream = BitInput.create(ticket.data());
+                BigInteger lotteryAccountKey = stream.readUnsignedBigInteger(128);
+                BigInteger playerAccountKey = stream.readUnsignedBigInteger(128);
+                BigInteger ticketEntropy = stream.readUnsignedBigInteger(128);
+                BigInteger ticketCount = stream.readUnsignedBigInteger(128);
+                Assertions.assertThat(lotteryAccountKey).isEqualTo(VALID_LOTTERY_ID);
+                Assertions.assertThat(playerAccountKey).isEqualTo(accountKey(player1));
+                Assertions.assertThat(ticketEntropy).isGreaterThan(BigInteger.ZERO);
+                Assertions.assertThat(ticketCount).isGreaterThan(BigInteger.ZERO);
+
+                // Pre-draw secret state assertions
+                SecretLotteryState preSecretState = getSecretLotteryState(VALID_LOTTERY_ID);
+                Assertions.assertThat(preSecretState).isNotNull();
+                Assertions.assertThat(preSecretState.tickets()).isGreaterThan(BigInteger.ZERO); // Tickets should be
+                                                                                                // purchased
+                Assertions.assertThat(preSecretState.entropy()).isNotNull(); // Entropy should be set
+
+                // Draw the lottery winner
+                TxExecution execution = drawLottery(creator, VALID_LOTTERY_ID);
+
+                // Assert the lottery state after drawing
+                LotteryState lotteryState = getLotteryState(VALID_LOTTERY_ID);
+                Assertions.assertThat(lotteryState.status().discriminant()).isEqualTo(Lottery.LotteryStatusD.DRAWN);
+
+                // Assert the secret state of the lottery
+                SecretLotteryState secretState = getSecretLotteryState(VALID_LOTTERY_ID);
+                Assertions.assertThat(secretState).isNotNull();
+                Assertions.assertThat(secretState.tickets()).isGreaterThan(BigInteger.ZERO); // Tickets should be
+                                                                                             // purchased
+
+                // Assert that the winner is drawn and the prize pool is distributed
+                // Note: The actual winner logic is not implemented in this test, so we just
+                // check that the action executed successfully
+                Assertions.assertThat(execution.isSuccess()).isTrue();
+
+                // Winner is resolved from the owner of the winning ticket purchase
+                Assertions.assertThat(lotteryState.winner()).isEqualTo(player1);
+
+                // Check lottery balance is expected amount
+                // BigInteger ticketsBought = preSecretState.tickets();
+                // BigInteger expectedCreatorBalance =
+                // lotteryState.entryCost().multiply(ticketsBought).subtract();
+                assertLotterySecretBalance(VALID_LOTTERY_ID, lotteryState.prizePool());
+                // Check the creator's balance is updated as expected
+                assertSecretBalance(
+                                creator,
+                                toBigInteger(1499), // Creator should receive the ticket sales
+                                accountKey(creator) // Creator account key
+                );
+                // Prize is only paid out once claimed
+                assertSecretBalance(
+                                player1,
+                                toBigInteger(500),
+                                accountKey(player1) // Player account key
+                );
+        }
+
+        @ContractTest(previous = "testDrawLotteryAfterDeadline")
+        void testLotteryStatisticsPublished() {
+                LotteryState lotteryState = getLotteryState(VALID_LOTTERY_ID);
+                SecretLotteryState secretState = getSecretLotteryState(VALID_LOTTERY_ID);
+
+                Lottery.LotteryStatistics statistics = lotteryState.statistics();
+                Assertions.assertThat(statistics).isNotNull();
+                Assertions.assertThat(statistics.tickets()).isEqualTo(secretState.tickets());
+                Assertions.assertThat(statistics.purchasers()).isEqualTo(BigInteger.ONE); // Only player1 bought tickets
+                Assertions.assertThat(statistics.revenue())
+                                .isEqualTo(secretState.tickets().multiply(lotteryState.entryCost()));
+        }
+
+        @ContractTest(previous = "testCreateLottery")
+        void testLotteryStatisticsGrossRevenue() {
+                BigInteger lotteryId = BigInteger.valueOf(987987987L);
+                BigInteger voucherCode = BigInteger.valueOf(777777L);
+                BigInteger ticketCount = BigInteger.TWO;
+                BigInteger spend = LOTTERY_ENTRY_COST.multiply(ticketCount);
+                BigInteger reward = spend.divide(BigInteger.TEN); // 10% referral reward
+                long deadline = System.currentTimeMillis() + LOTTERY_DURATION_MS;
+
+                createLottery(
+                                creator,
+                                lotteryId, // Lottery account key
+                                accountKey(creator), // Creator account key
+                                entropy(), // Random seed
+                                deadline, // Deadline
+                                LOTTERY_ENTRY_COST, // Entry cost
+                                toBigInteger(1), // Prize pool
+                                defaultOptions()
+                                                .withReferralReward(10)
+                                                .withVouchers(1, BigInteger.ONE)
+                                                .build()
+                );
+
+                // Free voucher ticket
+                addVoucher(creator, lotteryId, voucherCode);
+                redeemVoucher(player1, lotteryId, accountKey(player1), entropy(), BigInteger.ONE, voucherCode);
+
+                // Paid tickets, referred by player2
+                createSecretAccount(entropy(), player2);
+                purchaseTickets(
+                                player1,
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                ticketCount,
+                                accountKey(player2) // Referrer account key
+                );
+                assertSecretBalance(player2, reward, accountKey(player2));
+
+                // Failed purchases are not counted
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(player3, lotteryId, accountKey(player1), entropy(), ticketCount);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot purchase lottery tickets");
+                Assertions.assertThatThrownBy(() -> {
+                        purchaseTickets(player1, lotteryId, accountKey(player1), entropy(), BigInteger.valueOf(100000000));
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not purchase lottery ticket");
+
+                blockchain.waitForBlockProductionTime(deadline + 1);
+                drawLottery(creator, lotteryId);
+
+                Lottery.LotteryStatistics statistics = getLotteryState(lotteryId).statistics();
+                Assertions.assertThat(statistics).isNotNull();
+                Assertions.assertThat(statistics.tickets()).isEqualTo(ticketCount.add(BigInteger.ONE));
+                Assertions.assertThat(statistics.purchasers()).isEqualTo(BigInteger.ONE);
+                // Gross revenue, the referral reward is paid out of it
+                Assertions.assertThat(statistics.revenue()).isEqualTo(spend);
+        }
+
+        @ContractTest(previous = "testDrawLotteryAfterDeadline")
+        void testClaimWinningPrize() {
+                // Confirm player1 has expected balance before claiming winnings
+                assertSecretBalance(
+                                player1,
+                                toBigInteger(500), // After drawing, player should have 500 in balance
+                                accountKey(player1) // Player account key
+                );
+
+                claimWinnings(player1, VALID_LOTTERY_ID);
+
+                // Assert the secret balance of the player after claiming winnings
+                assertSecretBalance(
+                                player1,
+                                toBigInteger(501), // Player should receive the prize pool
+                                accountKey(player1) // Player account key
+                );
+        }
+
+        @ContractTest(previous = "testDrawLotteryAfterDeadline")
+        void testMigrateAccount() {
+                BigInteger accountKey = accountKey(player1);
+
+                // Migration requires the account key
+                Assertions.assertThatThrownBy(() -> {
+                        migrateAccount(player3, player1, accountKey(creator));
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not migrate account");
+                Assertions.assertThat(getLotteryContractState().userAccounts().get(player3)).isNull();
+
+                migrateAccount(player3, player1, accountKey);
+
+                // Balance and account key move to the new address
+                Assertions.assertThat(getLotteryContractState().userAccounts().get(player1)).isNull();
+                Assertions.assertThat(accountKey(player3)).isEqualTo(accountKey);
+                assertSecretBalance(player3, toBigInteger(500), accountKey);
+                Assertions.assertThat(getLotteryContractState().journal().entries())
+                                .extracting(entry -> entry.event().discriminant())
+                                .contains(Lottery.JournalEventD.ACCOUNT_MIGRATED);
+
+                // Unclaimed win follows the account
+                claimWinnings(player3, VALID_LOTTERY_ID);
+                assertSecretBalance(player3, toBigInteger(501), accountKey);
+                Assertions.assertThat(getLotteryState(VALID_LOTTERY_ID).status().discriminant())
+                                .isEqualTo(Lottery.LotteryStatusD.COMPLETE);
+        }
+
+        @ContractTest(previous = "testMigrateAccount")
+        void testCreateAccountAtMigratedAddress() {
+                BigInteger accountKey = accountKey(player3);
+                BigInteger tokenBalance = balance(player1);
+
+                // The old address redirects to the migrated account, so it cannot hold an account again
+                Assertions.assertThatThrownBy(() -> {
+                        createSecretAccount(entropy(), player1);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot create new user at an address whose account was migrated");
+                Assertions.assertThatThrownBy(() -> {
+                        createSecretAccountWithDeposit(entropy(), player1, toBigInteger(100));
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot create new user at an address whose account was migrated");
+                Assertions.assertThat(getLotteryContractState().userAccounts().get(player1)).isNull();
+                Assertions.assertThat(balance(player1)).isEqualTo(tokenBalance);
+
+                // Nor create lotteries, whose sales would be credited to the migrated account
+                Assertions.assertThatThrownBy(() -> {
+                        createLottery(
+                                        player1,
+                                        BigInteger.valueOf(147147147L), // Lottery account key
+                                        accountKey, // Creator account key
+                                        entropy(), // Random seed
+                                        System.currentTimeMillis() + (LOTTERY_DURATION_MS * 2), // Deadline
+                                        LOTTERY_ENTRY_COST, // Entry cost
+                                        toBigInteger(1) // Prize pool
+                        );
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Creator must have an account to create a lottery");
+
+                // Other accounts cannot take over the old address
+                createSecretAccount(entropy(), player2);
+                Assertions.assertThatThrownBy(() -> {
+                        migrateAccount(player1, player2, accountKey(player2));
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot migrate an account to an address that was migrated elsewhere");
+
+                // The migrated account itself can return
+                migrateAccount(player1, player3, accountKey);
+                Assertions.assertThat(getLotteryContractState().userAccounts().get(player3)).isNull();
+                assertSecretBalance(player1, toBigInteger(501), accountKey);
+        }
+
+        @ContractTest(previous = "testDrawLotteryAfterDeadline")
+        void testCloseAccount() {
+                // Prize must be claimed first
+                Assertions.assertThatThrownBy(() -> {
+                        closeAccount(player1);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot close an account with unclaimed prizes");
+
+                claimWinnings(player1, VALID_LOTTERY_ID);
+
+                BigInteger tokenBalance = balance(player1);
+                closeAccount(player1);
+
+                // Entire balance is withdrawn, and the account is gone
+                Assertions.assertThat(balance(player1)).isEqualTo(tokenBalance.add(toBigInteger(501)));
+                Assertions.assertThat(getLotteryContractState().userAccounts().get(player1)).isNull();
+                Assertions.assertThat(getLotteryContractState().accountKeys().get(player1)).isNull();
+                Assertions.assertThat(getLotteryContractState().journal().entries())
+                                .extracting(entry -> entry.event().discriminant())
+                                .contains(Lottery.JournalEventD.ACCOUNT_CLOSED);
+
+                // Closed accounts cannot be closed again
+                Assertions.assertThatThrownBy(() -> {
+                        closeAccount(player1);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Cannot close an account that does not exist");
+        }
+
+        @ContractTest(previous = "testPurchaseTickets")
+        void testCloseAccountWithUndrawnTickets() {
+                BigInteger balance = toBigInteger(1000).subtract(LOTTERY_ENTRY_COST.multiply(BigInteger.valueOf(5)));
+
+                // Any of the tickets may still win, and the prize is credited to the account
+                Assertions.assertThatThrownBy(() -> {
+                        closeAccount(player1);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining(
+                                                "Cannot close an account with tickets in lotteries that have not been drawn");
+
+                Assertions.assertThat(getLotteryContractState().userAccounts().get(player1)).isNotNull();
+                assertSecretBalance(player1, balance, accountKey(player1));
+        }
+
+        @ContractTest(previous = "testPurchaseCredits")
+        void testDrawOnlyAnonymousLottery() {
+                BigInteger lotteryId = ANONYMOUS_LOTTERY_ID;
+                long deadline = System.currentTimeMillis() + LOTTERY_DURATION_MS;
+
+                createLottery(
+                                player1,
+                                lotteryId, // Lottery account key
+                                accountKey(player1), // Creator account key
+                                entropy(), // Random seed
+                                deadline, // Deadline
+                                LOTTERY_ENTRY_COST, // Entry cost
+                                toBigInteger(1), // Prize pool
+                                defaultOptions().withCreatorEntry().withAnonymousWinner().build());
+                // The creator enters their own lottery, as the only account with credits
+                purchaseTickets(
+                                player1,
+                                lotteryId,
+                                accountKey(player1), // Player account key
+                                entropy(), // Random seed
+                                BigInteger.ONE, // Number of tickets to purchase
+                                BigInteger.ZERO, // No referrer account key
+                                BigInteger.ZERO, // No access code
+                                CLAIM_SECRET);
+                blockchain.waitForBlockProductionTime(deadline + 1);
+                drawLottery(player1, lotteryId);
+
+                Assertions.assertThat(getLotteryState(lotteryId).status().discriminant())
+                                .isEqualTo(Lottery.LotteryStatusD.DRAWN);
+        }
+
+        @ContractTest(previous = "testDrawOnlyAnonymousLottery")
+        void testCloseAccountWithAnonymousPrize() {
+                BigInteger lotteryId = ANONYMOUS_LOTTERY_ID;
+                BigInteger prizePool = toBigInteger(1);
+
+                // The anonymous winner is unknown, so closing the account is not held back
+                BigInteger tokenBalance = balance(player1);
+                closeAccount(player1);
+                Assertions.assertThat(balance(player1)).isEqualTo(tokenBalance.add(toBigInteger(999)));
+                Assertions.assertThat(getLotteryContractState().userAccounts().get(player1)).isNull();
+
+                // The prize remains claimable as a payout, which needs no account
+                BigInteger payoutBalance = balance(player3);
+                claimToPayoutAddress(player3, lotteryId, player3, CLAIM_SECRET);
+                Assertions.assertThat(balance(player3)).isEqualTo(payoutBalance.add(prizePool));
+                Assertions.assertThat(getLotteryState(lotteryId).status().discriminant())
+                                .isEqualTo(Lottery.LotteryStatusD.COMPLETE);
+        }
+
+        @ContractTest(previous = "testDrawOnlyAnonymousLottery")
+        void testRotateAccountKeyWithAnonymousPrize() {
+                BigInteger lotteryId = ANONYMOUS_LOTTERY_ID;
+                BigInteger oldAccountKey = accountKey(player1);
+
+                // Rotating after the draw is allowed, as the prize is claimed with the claim secret
+                rotateAccountKey(player1, oldAccountKey, entropy());
+                Assertions.assertThat(accountKey(player1)).isNotEqualTo(oldAccountKey);
+
+                // The old account key does not release the prize, while the claim secret still does
+                Assertions.assertThatThrownBy(() -> {
+                        claimToPayoutAddress(player3, lotteryId, player3, oldAccountKey);
+                }).isInstanceOf(RuntimeException.class)
+                                .hasMessageContaining("Could not claim prize");
+
+                BigInteger payoutBalance = balance(player3);
+                claimToPayoutAddress(player3, lotteryId, player3, CLAIM_SECRET);
+                Assertions.assertThat(balance(player3)).isEqualTo(payoutBalance.add(toBigInteger(1)));
+        }
+
+        @ContractTest(previous = "testClaimWinningPrize")
+        void testJournalRecordsLotteryLifecycle() {
+                Lottery.Journal journal = getLotteryContractState().journal();
+                List<Lottery.JournalEntry> entries = journal.entries();
+
+                Assertions.assertThat(entries)
+                                .extracting(entry -> entry.event().discriminant())
+                                .containsSubsequence(
+                                                Lottery.JournalEventD.ACCOUNT_CREATED,
+                                                Lottery.JournalEventD.LOTTERY_CREATED,
+                                                Lottery.JournalEventD.LOTTERY_OPENED,
+                                                Lottery.JournalEventD.LOTTERY_CLOSED,
+                                                Lottery.JournalEventD.LOTTERY_DRAWN,
+                                                Lottery.JournalEventD.LOTTERY_CLAIMED);
+
+                // Sequence numbers are consecutive, and the next sequence follows the last entry
+                for (int i = 1; i < entries.size(); i++) {
+                        Assertions.assertThat(entries.get(i).sequence()).isEqualTo(entries.get(i - 1).sequence() + 1);
+                        Assertions.assertThat(entries.get(i).timestamp())
+                                        .isGreaterThanOrEqualTo(entries.get(i - 1).timestamp());
+                }
+                Assertions.assertThat(journal.nextSequence())
+                                .isEqualTo(entries.get(entries.size() - 1).sequence() + 1);
+        }
+
+        @ContractTest(previous = "testClaimWinningPrize")
+        void testLotteryLifecycleTimestamps() {
+                LotteryState lotteryState = getLotteryState(VALID_LOTTERY_ID);
+
+                Assertions.assertThat(lotteryState.opened()).isNotNull();
+                Assertions.assertThat(lotteryState.closed()).isNotNull();
+                Assertions.assertThat(lotteryState.drawn()).isNotNull();
+                Assertions.assertThat(lotteryState.claimed()).isNotNull();
+
+                // Transitions happen in order, and the lottery closes after its deadline
+                Assertions.assertThat(lotteryState.opened().timestamp()).isGreaterThanOrEqualTo(lotteryState.createdAt());
+                Assertions.assertThat(lotteryState.closed().timestamp())
+                                .isGreaterThanOrEqualTo(lotteryState.opened().timestamp())
+                                .isGreaterThan(lotteryState.deadline());
+                Assertions.assertThat(lotteryState.drawn().timestamp())
+                                .isGreaterThanOrEqualTo(lotteryState.closed().timestamp());
+                Assertions.assertThat(lotteryState.claimed().timestamp())
+                                .isGreaterThanOrEqualTo(lotteryState.drawn().timestamp());
+
+                // The lottery was closed by opening its secret state
+                Assertions.assertThat(lotteryState.closed().computationId()).isEqualTo(lotteryState.secretStateId());
+        }
+
+        @ContractTest(previous = "testClaimWinningPrize")
+        void testDrawWithNoTickets() {
+                // Create a fresh lottery
+                BigInteger lotteryId = BigInteger.valueOf(999999999L); // New lottery account key
+                BigInteger prizePool = toBigInteger(100); // Prize pool
+                long deadline = System.currentTimeMillis() + (LOTTERY_DURATION_MS * 2); // Deadline (later than previous
+                                                                                        // tests since we've already
+                                                                                        // time travelled in this test
+                                                                                        // flow)
+
+                // Double check balance is as expected
+                assertSecretBalance(
+                                player1,
+                                toBigInteger(501), // Balance after winning the first lottery
+                                accountKey(player1) // Player account key
+                );
+
+                createLottery(
+                                player1,
+                                lotteryId, // Lottery account key
+                                accountKey(player1), // Creator account key
+                                entropy(), // Random seed
+                                deadline, // Deadline
+                                LOTTERY_ENTRY_COST, // Entry cost
+                                prizePool, // Prize pool
+                                defaultOptions().withoutStatistics().build()
                 );
 
                 // Assert balances
                 assertSecretBalance(
                                 player1,
-                                toBigInteger(1000).subtract(prizePool), // After creating the lottery, balance should be
+                                toBigInteger(501).subtract(prizePool), // After creating the lottery, balance should be
                                                                         // reduced by the prize pool
-                                BigInteger.valueOf(716473264415L) // Player account key
+                                accountKey(player1) // Player account key
                 );
                 assertLotterySecretBalance(
                                 lotteryId, // Lottery account key
@@ -489,8 +1779,8 @@ final class LotteryTest extends JunitContractTest {
                 // All the pool should go back to the creator
                 assertSecretBalance(
                                 player1,
-                                toBigInteger(1000), // Player should receive the prize pool back
-                                BigInteger.valueOf(716473264415L) // Player account key
+                                toBigInteger(501), // Player should receive the prize pool back
+                                accountKey(player1) // Player account key
                 );
                 // Assert the lottery state after drawing
                 LotteryState lotteryState = getLotteryState(lotteryId);
@@ -499,6 +1789,12 @@ final class LotteryTest extends JunitContractTest {
                 // winner
                 Assertions.assertThat(lotteryState.status().discriminant()).isEqualTo(Lottery.LotteryStatusD.COMPLETE);
 
+                // Drawn is recorded, but nothing was ever claimed
+                Assertions.assertThat(lotteryState.drawn()).isNotNull();
+                Assertions.assertThat(lotteryState.claimed()).isNull();
+
+                // Creator opted out of statistics
+                Assertions.assertThat(lotteryState.statistics()).isNull();
         }
 
         // Helper methods for setup
@@ -546,18 +1842,59 @@ final class LotteryTest extends JunitContractTest {
                 Assertions.assertThat(allowance(player2, lottery)).isEqualTo(PLAYER_INITIAL_BALANCE);
         }
 
-        private PendingInputId createSecretAccount(BigInteger accountKey, BlockchainAddress wallet) {
+        private PendingInputId createSecretAccount(BigInteger randomness, BlockchainAddress wallet) {
+
+                SecretInput input = Lottery.createAccount().secretInput(
+                                new AccountCreationSecret(randomness));
+
+                return blockchain.sendSecretInput(
+                                lottery,
+                                wallet,
+                                input.secretInput(),
+                                input.publicRpc());
+
+        }
+
+        private PendingInputId createSecretAccountWithDeposit(BigInteger randomness, BlockchainAddress wallet,
+                        BigInteger amount) {
+
+                SecretInput input = Lottery.createAccountWithDeposit(
+                                amount).secretInput(
+                                                new AccountCreationSecret(randomness));
+
+                return blockchain.sendSecretInput(
+                                lottery,
+                                wallet,
+                                input.secretInput(),
+                                input.publicRpc());
+        }
 
-                SecretInput input = Lottery.createAccount(
-                                accountKey).secretInput(
-                                                new AccountCreationSecret(accountKey));
+        private PendingInputId rotateAccountKey(
+                        BlockchainAddress wallet,
+                        BigInteger oldAccountKey,
+                        BigInteger randomness) {
+                SecretInput input = Lottery.rotateAccountKey().secretInput(
+                                new AccountKeyRotationSecret(oldAccountKey, randomness));
 
                 return blockchain.sendSecretInput(
                                 lottery,
                                 wallet,
                                 input.secretInput(),
                                 input.publicRpc());
+        }
+
+        private PendingInputId migrateAccount(
+                        BlockchainAddress wallet,
+                        BlockchainAddress from,
+                        BigInteger accountKey) {
+                SecretInput input = Lottery.migrateAccount(from).secretInput(
+                                new AccountMigrationSecret(accountKey));
 
+                return blockchain.sendSecretInput(
+                                lottery,
+                                wallet,
+                                input.secretInput(),
+                                input.publicRpc());
         }
 
         private TxExecution purchaseCredits(BlockchainAddress wallet, BigInteger credits) {
@@ -565,6 +1902,46 @@ final class LotteryTest extends JunitContractTest {
                 return blockchain.sendAction(wallet, lottery, action);
         }
 
+        private TxExecution setOwnershipPolicy(BlockchainAddress wallet, Lottery.OwnershipPolicy policy) {
+                byte[] action = Lottery.setOwnershipPolicy(policy);
+                return blockchain.sendAction(wallet, lottery, action);
+        }
+
+        private TxExecution transferAdmin(BlockchainAddress wallet, BlockchainAddress newAdmin) {
+                byte[] action = Lottery.transferAdmin(newAdmin);
+                return blockchain.sendAction(wallet, lottery, action);
+        }
+
+        private TxExecution setApi(BlockchainAddress wallet, BlockchainAddress api) {
+                byte[] action = Lottery.setApi(api);
+                return blockchain.sendAction(wallet, lottery, action);
+        }
+
+        private TxExecution setToken(BlockchainAddress wallet, BlockchainAddress token) {
+                byte[] action = Lottery.setToken(token);
+                return blockchain.sendAction(wallet, lottery, action);
+        }
+
+        private TxExecution setPauseFlags(BlockchainAddress wallet, Lottery.PauseFlags paused) {
+                byte[] action = Lottery.setPauseFlags(paused);
+                return blockchain.sendAction(wallet, lottery, action);
+        }
+
+        private TxExecution setRealOwnership(BlockchainAddress wallet, boolean enabled) {
+                byte[] action = Lottery.setRealOwnership(enabled);
+                return blockchain.sendAction(wallet, lottery, action);
+        }
+
+        private TxExecution revealMyBalance(BlockchainAddress wallet) {
+                byte[] action = Lottery.revealMyBalance();
+                return blockchain.sendAction(wallet, lottery, action);
+        }
+
+        private TxExecution closeAccount(BlockchainAddress wallet) {
+                byte[] action = Lottery.closeAccount();
+                return blockchain.sendAction(wallet, lottery, action);
+        }
+
         private TxExecution redeemCredits(BlockchainAddress wallet, BigInteger credits) {
                 byte[] action = Lottery.redeemCredits(credits);
                 return blockchain.sendAction(wallet, lottery, action);
@@ -575,6 +1952,21 @@ final class LotteryTest extends JunitContractTest {
                 return blockchain.sendAction(wallet, lottery, action);
         }
 
+        private PendingInputId claimToPayoutAddress(
+                        BlockchainAddress wallet,
+                        BigInteger lottery_account_key,
+                        BlockchainAddress payout_address,
+                        BigInteger claim_secret) {
+                SecretInput input = Lottery.claimToPayoutAddress(lottery_account_key, payout_address).secretInput(
+                                new PrizeClaimSecret(claim_secret));
+
+                return blockchain.sendSecretInput(
+                                lottery,
+                                wallet,
+                                input.secretInput(),
+                                input.publicRpc());
+        }
+
         private PendingInputId createLottery(
                         BlockchainAddress wallet,
                         BigInteger lottery_account_key,
@@ -583,17 +1975,136 @@ final class LotteryTest extends JunitContractTest {
                         long deadline,
                         BigInteger entry_cost,
                         BigInteger prize_pool) {
+                return createLottery(
+                                wallet,
+                                lottery_account_key,
+                                creator_account_key,
+                                random_seed,
+                                deadline,
+                                entry_cost,
+                                prize_pool,
+                                defaultOptions().build());
+        }
+
+        /**
+         * Options of a public lottery publishing its statistics, without referral rewards, discounts,
+         * vouchers or creator entry. Adjust them with the {@code with} methods.
+         */
+        private static OptionsBuilder defaultOptions() {
+                return new OptionsBuilder();
+        }
+
+        /** Builds {@link LotteryOptions} without spelling out every field. */
+        private static final class OptionsBuilder {
+                private boolean publishStatistics = true;
+                private byte referralRewardPercentage = 0;
+                private List<DiscountTier> discountTiers = List.of();
+                private Allowlist allowlist = null;
+                private int voucherCount = 0;
+                private BigInteger ticketsPerVoucher = BigInteger.ZERO;
+                private boolean giveaway = false;
+                private boolean allowCreatorEntry = false;
+                private boolean anonymousWinner = false;
+
+                OptionsBuilder withoutStatistics() {
+                        publishStatistics = false;
+                        return this;
+                }
+
+                OptionsBuilder withReferralReward(int percentage) {
+                        referralRewardPercentage = (byte) percentage;
+                        return this;
+                }
+
+                OptionsBuilder withDiscountTiers(DiscountTier... tiers) {
+                        discountTiers = List.of(tiers);
+                        return this;
+                }
+
+                OptionsBuilder withAllowlist(Allowlist members) {
+                        allowlist = members;
+                        return this;
+                }
+
+                OptionsBuilder withVouchers(int count, BigInteger ticketsEach) {
+                        voucherCount = count;
+                        ticketsPerVoucher = ticketsEach;
+                        return this;
+                }
+
+                OptionsBuilder withGiveaway() {
+                        giveaway = true;
+                        return this;
+                }
+
+                OptionsBuilder withCreatorEntry() {
+                        allowCreatorEntry = true;
+                        return this;
+                }
+
+                OptionsBuilder withAnonymousWinner() {
+                        anonymousWinner = true;
+                        return this;
+                }
+
+                LotteryOptions build() {
+                        return new LotteryOptions(
+                                        publishStatistics,
+                                        referralRewardPercentage,
+                                        discountTiers,
+                                        allowlist,
+                                        voucherCount,
+                                        ticketsPerVoucher,
+                                        giveaway,
+                                        allowCreatorEntry,
+                                        anonymousWinner);
+                }
+        }
+
+        private PendingInputId createLottery(
+                        BlockchainAddress wallet,
+                        BigInteger lottery_account_key,
+                        BigInteger creator_account_key,
+                        BigInteger random_seed,
+                        long deadline,
+                        BigInteger entry_cost,
+                        BigInteger prize_pool,
+                        LotteryOptions options) {
+                return createLottery(
+                                wallet,
+                                lottery_account_key,
+                                creator_account_key,
+                                random_seed,
+                                deadline,
+                                entry_cost,
+                                prize_pool,
+                                options,
+                                BigInteger.ZERO);
+        }
+
+        private PendingInputId createLottery(
+                        BlockchainAddress wallet,
+                        BigInteger lottery_account_key,
+                        BigInteger creator_account_key,
+                        BigInteger random_seed,
+                        long deadline,
+                        BigInteger entry_cost,
+                        BigInteger prize_pool,
+                        LotteryOptions options,
+                        BigInteger access_code) {
 
                 // Assertions.assertThat(prize_pool).isNull();
                 SecretInput input = Lottery.createLottery(
                                 lottery_account_key,
                                 deadline,
                                 entry_cost,
-                                prize_pool).secretInput(
+                                prize_pool,
+                                options).secretInput(
                                                 new LotteryCreationSecret(
                                                                 lottery_account_key,
                                                                 creator_account_key,
-                                                                random_seed));
+                                                                random_seed,
+                                                                access_code));
 
                 return blockchain.sendSecretInput(
                                 lottery,
@@ -608,13 +2119,137 @@ final class LotteryTest extends JunitContractTest {
                         BigInteger player_account_key,
                         BigInteger random_seed,
                         BigInteger ticketCount) {
+                return purchaseTickets(
+                                wallet,
+                                lottery_account_key,
+                                player_account_key,
+                                random_seed,
+                                ticketCount,
+                                BigInteger.ZERO);
+        }
+
+        private PendingInputId purchaseTickets(
+                        BlockchainAddress wallet,
+                        BigInteger lottery_account_key,
+                        BigInteger player_account_key,
+                        BigInteger random_seed,
+                        BigInteger ticketCount,
+                        BigInteger referrer_account_key) {
+                return purchaseTickets(
+                                wallet,
+                                lottery_account_key,
+                                player_account_key,
+                                random_seed,
+                                ticketCount,
+                                referrer_account_key,
+                                BigInteger.ZERO);
+        }
+
+        private PendingInputId purchaseTickets(
+                        BlockchainAddress wallet,
+                        BigInteger lottery_account_key,
+                        BigInteger player_account_key,
+                        BigInteger random_seed,
+                        BigInteger ticketCount,
+                        BigInteger referrer_account_key,
+                        BigInteger access_code) {
+                return purchaseTickets(
+                                wallet,
+                                lottery_account_key,
+                                player_account_key,
+                                random_seed,
+                                ticketCount,
+                                referrer_account_key,
+                                access_code,
+                                BigInteger.ZERO);
+        }
+
+        private PendingInputId purchaseTickets(
+                        BlockchainAddress wallet,
+                        BigInteger lottery_account_key,
+                        BigInteger player_account_key,
+                        BigInteger random_seed,
+                        BigInteger ticketCount,
+                        BigInteger referrer_account_key,
+                        BigInteger access_code,
+                        BigInteger claim_secret) {
                 SecretInput input = Lottery.purchaseTickets(
                                 lottery_account_key).secretInput(
                                                 new LotteryTicketPurchaseSecret(
                                                                 lottery_account_key,
                                                                 player_account_key,
                                                                 ticketCount,
-                                                                random_seed));
+                                                                random_seed,
+                                                                referrer_account_key,
+                                                                access_code,
+                                                                BigInteger.ZERO,
+                                                                claim_secret));
+
+                return blockchain.sendSecretInput(
+                                lottery,
+                                wallet,
+                                input.secretInput(),
+                                input.publicRpc());
+        }
+
+        private PendingInputId purchaseTicketsWithTokens(
+                        BlockchainAddress wallet,
+                        BigInteger lottery_account_key,
+                        BigInteger player_account_key,
+                        BigInteger random_seed,
+                        BigInteger paidTicketCount,
+                        BigInteger secretTicketCount) {
+                SecretInput input = Lottery.purchaseTicketsWithTokens(
+                                lottery_account_key,
+                                paidTicketCount).secretInput(
+                                                new LotteryTicketPurchaseSecret(
+                                                                lottery_account_key,
+                                                                player_account_key,
+                                                                secretTicketCount,
+                                                                random_seed,
+                                                                BigInteger.ZERO,
+                                                                BigInteger.ZERO,
+                                                                BigInteger.ZERO,
+                                                                BigInteger.ZERO));
+
+                return blockchain.sendSecretInput(
+                                lottery,
+                                wallet,
+                                input.secretInput(),
+                                input.publicRpc());
+        }
+
+        private PendingInputId addVoucher(
+                        BlockchainAddress wallet,
+                        BigInteger lottery_account_key,
+                        BigInteger code) {
+                SecretInput input = Lottery.addVoucher(lottery_account_key).secretInput(
+                                new VoucherSecret(lottery_account_key, code));
+
+                return blockchain.sendSecretInput(
+                                lottery,
+                                wallet,
+                                input.secretInput(),
+                                input.publicRpc());
+        }
+
+        private PendingInputId redeemVoucher(
+                        BlockchainAddress wallet,
+                        BigInteger lottery_account_key,
+                        BigInteger player_account_key,
+                        BigInteger random_seed,
+                        BigInteger ticketCount,
+                        BigInteger code) {
+                SecretInput input = Lottery.redeemVoucher(lottery_account_key).secretInput(
+                                new LotteryTicketPurchaseSecret(
+                                                lottery_account_key,
+                                                player_account_key,
+                                                ticketCount,
+                                                random_seed,
+                                                BigInteger.ZERO,
+                                                BigInteger.ZERO,
+                                                code,
+                                                BigInteger.ZERO));
 
                 return blockchain.sendSecretInput(
                                 lottery,
@@ -623,6 +2258,13 @@ final class LotteryTest extends JunitContractTest {
                                 input.publicRpc());
         }
 
+        private TxExecution revealSalesToCreator(BlockchainAddress wallet, BigInteger lotteryId) {
+
+                byte[] action = Lottery.revealSalesToCreator(lotteryId);
+
+                return blockchain.sendAction(wallet, lottery, action);
+        }
+
         private TxExecution drawLottery(BlockchainAddress wallet, BigInteger lotteryId) {
 
                 byte[] action = Lottery.drawWinner(lotteryId);
@@ -716,9 +2358,37 @@ final class LotteryTest extends JunitContractTest {
                 // Assertions.assertThat(realOwner).isEqualTo(assertOwnerString);
         }
 
+        private void assertBalanceOwner(BlockchainAddress account, BlockchainAddress assertOwner) {
+                int variableId = getLotteryContractState().userAccounts().get(account).rawId();
+
+                String realOwner = "";
+                JsonNode variablesNode = blockchain.getContractStateJson(lottery).getNode("/variables");
+
+                for (int i = 0; i < variablesNode.size(); i++) {
+                        final int id = variablesNode.get(i).get("value").get("id").asInt();
+                        if (id == variableId) {
+                                realOwner = variablesNode.get(i).get("value").get("owner").asText();
+                                break;
+                        }
+                }
+
+                Assertions.assertThat(realOwner).isEqualTo(assertOwner.writeAsString());
+        }
+
         record AccountBalance(BigInteger accountKey, BigInteger balance) {
         }
 
+        private BigInteger accountKey(BlockchainAddress owner) {
+                Lottery.SecretVarId varId = getLotteryContractState().accountKeys().get(owner);
+
+                Assertions.assertThat(varId).isNotNull();
+
+                CompactBitArray varVal = zkNodes.getSecretVariable(lottery, varId.rawId());
+
+                Assertions.assertThat(varVal.data().length).isEqualTo(16);
+                return BitInput.create(varVal.data()).readUnsignedBigInteger(128);
+        }
+
         private void assertSecretBalance(BlockchainAddress assetOwner, BigInteger expectedBalance,
                         BigInteger... accountKey) {
                 Lottery.ContractState cstate = getLotteryContractState();
@@ -0,0 +1,149 @@
+//! This module contains the activity journal of the lottery contract.
+//! The journal is a bounded, append-only record of lifecycle events kept in
+//! public state, so that indexers can follow what happened in the contract
+//! without having to diff the contract state.
+
+use std::collections::VecDeque;
+
+use create_type_spec_derive::CreateTypeSpec;
+use read_write_state_derive::ReadWriteState;
+
+use pbc_contract_common::address::Address;
+
+use crate::lottery::LotteryId;
+use crate::{OwnershipPolicy, PauseFlags};
+
+/// Maximum number of entries kept in the journal.
+/// Once reached, the oldest entry is dropped for every new entry.
+pub const MAX_JOURNAL_ENTRIES: usize = 200;
+
+/// Sequence number of a journal entry.
+/// Strictly increasing and never reused, even when old entries are dropped.
+pub type JournalSequence = u64;
+
+/// Lifecycle event recorded in the journal
+#[derive(CreateTypeSpec, ReadWriteState, Clone, Debug)]
+pub enum JournalEvent {
+    /// Lottery has been created and is pending payment by the creator
+    #[discriminant(1)]
+    LotteryCreated {
+        lottery_id: LotteryId,
+        creator: Address,
+    },
+
+    /// Lottery has been funded and is accepting entries
+    #[discriminant(2)]
+    LotteryOpened { lottery_id: LotteryId },
+
+    /// Entry period has closed and winner selection has started
+    #[discriminant(3)]
+    LotteryClosed { lottery_id: LotteryId },
+
+    /// Winner has been selected. No winner means no tickets were sold.
+    /// Anonymous winners are recorded as [`JournalEvent::LotteryDrawnAnonymously`]
+    #[discriminant(4)]
+    LotteryDrawn {
+        lottery_id: LotteryId,
+        winner: Option<Address>,
+    },
+
+    /// Winner has claimed their prize
+    #[discriminant(5)]
+    LotteryClaimed {
+        lottery_id: LotteryId,
+        winner: Address,
+    },
+
+    /// User account has been created
+    #[discriminant(6)]
+    AccountCreated { account: Address },
+
+    /// A work item failed, see [`crate::fail_safely`]
+    #[discriminant(7)]
+    WorkItemFailed {
+        /// Account the work item was performed for, if any
+        account: Option<Address>,
+        /// Lottery the work item was performed for, if any
+        lottery_id: Option<LotteryId>,
+        error_message: String,
+    },
+
+    /// Winner has been selected, but is kept secret,
+    /// see [`crate::lottery::LotteryOptions::anonymous_winner`]
+    #[discriminant(8)]
+    LotteryDrawnAnonymously { lottery_id: LotteryId },
+
+    /// Anonymous winner has claimed their prize
+    #[discriminant(9)]
+    LotteryClaimedAnonymously { lottery_id: LotteryId },
+
+    /// User account has been migrated to a new address, see [`crate::migrate_account`]
+    #[discriminant(10)]
+    AccountMigrated { from: Address, to: Address },
+
+    /// User account has been closed, and its balance withdrawn
+    #[discriminant(11)]
+    AccountClosed { account: Address },
+
+    /// Admin has switched the [`OwnershipPolicy`], see [`crate::set_ownership_policy`]
+    #[discriminant(12)]
+    OwnershipPolicyChanged { policy: OwnershipPolicy },
+
+    /// Admin role has been transferred to a new address
+    #[discriminant(13)]
+    AdminTransferred { from: Address, to: Address },
+
+    /// API address has been rotated, and the balances it owned re-owned
+    #[discriminant(14)]
+    ApiRotated { from: Address, to: Address },
+
+    /// Token used for credits has been replaced
+    #[discriminant(15)]
+    TokenUpdated { from: Address, to: Address },
+
+    /// Admin has paused or resumed entry points, see [`crate::set_pause_flags`]
+    #[discriminant(16)]
+    PauseFlagsChanged { paused: PauseFlags },
+}
+
+/// A single entry in the [`Journal`]
+#[derive(CreateTypeSpec, ReadWriteState, Clone, Debug)]
+pub struct JournalEntry {
+    /// Sequence number of this entry
+    pub sequence: JournalSequence,
+
+    /// Block production time at which the event happened
+    pub timestamp: i64,
+
+    /// The event that happened
+    pub event: JournalEvent,
+}
+
+/// Bounded, append-only journal of lifecycle events.
+///
+/// Indexers can tail the journal by remembering the last seen [`JournalEntry::sequence`].
+/// A gap between the remembered sequence and the first entry means entries have been dropped.
+#[derive(CreateTypeSpec, ReadWriteState, Debug, Default)]
+pub struct Journal {
+    /// Sequence number that will be assigned to the next entry
+    pub next_sequence: JournalSequence,
+
+    /// Most recent entries, oldest first
+    pub entries: VecDeque<JournalEntry>,
+}
+
+impl Journal {
+    /// Append an event to the journal, dropping the oldest entry if the journal is full
+    pub fn record(&mut self, timestamp: i64, event: JournalEvent) {
+        if self.entries.len() >= MAX_JOURNAL_ENTRIES {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(JournalEntry {
+            sequence: self.next_sequence,
+            timestamp,
+            event,
+        });
+        self.next_sequence += 1;
+    }
+}
@@ -14,10 +14,14 @@
 extern crate pbc_contract_codegen;
 extern crate pbc_contract_common;
 
-use std::collections::VecDeque;
+use std::collections::{BTreeSet, VecDeque};
 
 use create_type_spec_derive::CreateTypeSpec;
-use lottery::{LotteryId, LotteryState, LotteryStatus};
+use journal::{Journal, JournalEvent};
+use lottery::{
+    LotteryId, LotteryOptions, LotteryState, LotteryStatistics, LotteryStatus, LotteryTransition,
+    MAX_DISCOUNT_TIERS,
+};
 use mpc_20::MPC20Contract;
 use pbc_contract_common::address::Address;
 use pbc_contract_common::address::Shortname;
@@ -28,8 +32,11 @@ use pbc_contract_common::zk::CalculationStatus;
 use pbc_contract_common::zk::ZkClosed;
 use pbc_contract_common::zk::{SecretVarId, ZkInputDef, ZkState, ZkStateChange};
 use pbc_zk::SecretBinary;
+use read_write_rpc_derive::ReadWriteRPC;
 use read_write_state_derive::ReadWriteState;
 
+/// Public journal of lifecycle events
+mod journal;
 /// Core lottery data structures and state
 mod lottery;
 /// Interface for MPC20 token operations
@@ -61,7 +68,7 @@ pub enum VariableKind {
 
     /// Secret input for account creation
     #[discriminant(5)]
-    AccountCreationSecret { owner: Address, account_key: u128 },
+    AccountCreationSecret { owner: Address },
     /// Secret-share variable is a work item.
     ///
     /// Indicates that secret data is [`zk_compute::PendingTransferSecrets`] or [`zk_compute::AccountCreationSecret`], depending upon the computation.
@@ -100,6 +107,10 @@ pub enum VariableKind {
     LotteryTicketPurchaseSecretData {
         owner: Address,
         lottery_id: LotteryId,
+        /// Number of tickets paid for directly with tokens, see [`purchase_tickets_with_tokens`]
+        direct_tickets: Option<u128>,
+        /// Whether the tickets are paid with a voucher, see [`redeem_voucher`]
+        voucher: bool,
     },
     /// Result of a lottery ticket purchase operation
     #[discriminant(12)]
@@ -108,6 +119,9 @@ pub enum VariableKind {
         owner: Address,
         /// Lottery ID as provided in public input
         lottery_id: LotteryId,
+        /// Identifier of secret-shared [`zk_compute::LotteryTicketPurchaseSecret`], discarded if
+        /// the purchase fails
+        ticket_purchase_id: SecretVarId,
     },
     /// Result of a lottery winner draw operation
     #[discriminant(13)]
@@ -117,9 +131,94 @@ pub enum VariableKind {
     AccountCreationResult {
         /// Owner of the account
         owner: Address,
-        /// Account key as provided in public input
-        account_key: u128,
     },
+    /// Secret input for account creation with an initial deposit
+    #[discriminant(15)]
+    AccountCreationWithDepositSecret {
+        owner: Address,
+        /// Amount of tokens to deposit into the new account
+        amount: u128,
+    },
+    /// Statistics of a drawn lottery, see [`zk_compute::LotteryStatistics`]
+    #[discriminant(16)]
+    LotteryStatisticsResult { lottery_id: LotteryId },
+    /// Sales of an open lottery, see [`zk_compute::SalesReport`]
+    #[discriminant(17)]
+    SalesReport {
+        /// Creator of the lottery, the only one able to read the report
+        owner: Address,
+        lottery_id: LotteryId,
+    },
+    /// Voucher registered by the creator, see [`zk_compute::VoucherSecret`]
+    #[discriminant(18)]
+    Voucher { lottery_id: LotteryId },
+    /// Voucher that has been redeemed, see [`zk_compute::VoucherSecret`]
+    #[discriminant(19)]
+    SpentVoucher { lottery_id: LotteryId },
+    /// Result of a deposit operation
+    #[discriminant(20)]
+    DepositResult { owner: Address },
+    /// Account key of a user, see [`zk_compute::AccountKeySecret`]
+    #[discriminant(21)]
+    AccountKey { owner: Address },
+    /// Claim secret of the winning ticket purchase of a lottery, see
+    /// [`zk_compute::PrizeClaimSecret`]
+    #[discriminant(22)]
+    WinnerClaim { lottery_id: LotteryId },
+    /// Secret input for claiming the prize of an anonymous winner, see
+    /// [`zk_compute::PrizeClaimSecret`]
+    #[discriminant(23)]
+    PrizeClaimSecretData {
+        owner: Address,
+        lottery_id: LotteryId,
+        /// Address to pay the prize to in tokens, see [`claim_to_payout_address`]
+        payout_address: Address,
+    },
+    /// Result of a claim by an anonymous winner
+    #[discriminant(24)]
+    PrizeClaimResult {
+        /// Address that sent the claim, not necessarily linked to the winner
+        owner: Address,
+        lottery_id: LotteryId,
+        /// Address to pay the prize to in tokens
+        payout_address: Address,
+    },
+    /// Secret input for account key rotation, see [`zk_compute::AccountKeyRotationSecret`]
+    #[discriminant(25)]
+    AccountKeyRotationSecret { owner: Address },
+    /// Result of an account key rotation
+    #[discriminant(26)]
+    AccountKeyRotationResult {
+        /// Owner of the account
+        owner: Address,
+    },
+    /// Secret input for account migration, see [`zk_compute::AccountMigrationSecret`]
+    #[discriminant(27)]
+    AccountMigrationSecret {
+        /// Address to migrate the account to
+        owner: Address,
+        /// Address of the account to migrate
+        from: Address,
+    },
+    /// Result of an account migration
+    #[discriminant(28)]
+    AccountMigrationResult {
+        /// Address to migrate the account to
+        owner: Address,
+        /// Address of the account to migrate
+        from: Address,
+    },
+    /// Result of an account closure, holding the balance to withdraw
+    #[discriminant(29)]
+    AccountClosureResult { owner: Address },
+    /// Copy of the balance of a user account, only readable by the owner, see
+    /// [`reveal_my_balance`]
+    #[discriminant(30)]
+    BalanceReveal { owner: Address },
+    /// Updated balance of the referrer of a ticket purchase. Replaces the user account of the
+    /// referrer once the purchase result is opened, see [`purchase_tickets`]
+    #[discriminant(31)]
+    ReferrerAccount { lottery_id: LotteryId },
 }
 
 /// Indicates the type of the item in the work list.
@@ -130,7 +229,6 @@ pub enum WorkListItem {
     PendingAccountCreation {
         /// Account to create
         account: Address,
-        account_key: u128,
         /// Identifier of secret-shared [`zk_compute::AccountCreationSecret`].
         account_creation_id: SecretVarId,
     },
@@ -157,7 +255,6 @@ pub enum WorkListItem {
         account: Address,
         /// Lottery ID as provided in public input
         lottery_id: LotteryId,
-        /// Prize pool amount
         prize_pool: u128,
         /// Identifier of secret-shared [`zk_compute::LotteryCreationSecret`]
         lottery_creation_id: SecretVarId,
@@ -192,6 +289,131 @@ pub enum WorkListItem {
         /// Lottery ID as provided in public input
         lottery_id: LotteryId,
     },
+    /// Created by the [`account_deposit_callback`] once the tokens of a
+    /// [`create_account_with_deposit`] invocation have been received.
+    #[discriminant(9)]
+    PendingAccountCreationWithDeposit {
+        /// Account to create
+        account: Address,
+        /// Amount of tokens deposited, which becomes the initial balance
+        amount: u128,
+        /// Identifier of secret-shared [`zk_compute::AccountCreationSecret`].
+        account_creation_id: SecretVarId,
+    },
+    /// Created by the [`ticket_payment_callback`] once the tokens of a
+    /// [`purchase_tickets_with_tokens`] invocation have been received.
+    #[discriminant(10)]
+    PendingLotteryTicketDirectPurchase {
+        /// Account of the lottery ticket purchaser
+        account: Address,
+        /// Lottery ID as provided in public input
+        lottery_id: LotteryId,
+        /// Number of tickets paid for
+        tickets: u128,
+        /// Amount of tokens paid for the tickets
+        cost: u128,
+        /// Identifier of secret-shared [`zk_compute::LotteryTicketPurchaseSecret`]
+        ticket_purchase_id: SecretVarId,
+    },
+    /// Created by the opening of the draw result, if the creator chose to publish statistics.
+    #[discriminant(11)]
+    PendingLotteryStatistics {
+        /// Lottery ID as provided in public input
+        lottery_id: LotteryId,
+    },
+    /// Created by the [`reveal_sales_to_creator`] invocation.
+    #[discriminant(12)]
+    PendingSalesReveal {
+        /// Lottery ID as provided in public input
+        lottery_id: LotteryId,
+    },
+    /// Created by the [`redeem_voucher`] invocation.
+    #[discriminant(13)]
+    PendingVoucherRedemption {
+        /// Account of the lottery ticket purchaser
+        account: Address,
+        /// Lottery ID as provided in public input
+        lottery_id: LotteryId,
+        /// Identifier of secret-shared [`zk_compute::LotteryTicketPurchaseSecret`]
+        ticket_purchase_id: SecretVarId,
+    },
+    /// Created by the [`claim_to_payout_address`] invocation.
+    #[discriminant(14)]
+    PendingAnonymousClaimPrize {
+        /// Address that sent the claim
+        account: Address,
+        /// Lottery ID as provided in public input
+        lottery_id: LotteryId,
+        /// Identifier of secret-shared [`zk_compute::PrizeClaimSecret`]
+        prize_claim_id: SecretVarId,
+        /// Address to pay the prize to in tokens
+        payout_address: Address,
+    },
+    /// Created by the [`rotate_account_key`] invocation.
+    #[discriminant(15)]
+    PendingAccountKeyRotation {
+        /// Account to rotate the key of
+        account: Address,
+        /// Identifier of secret-shared [`zk_compute::AccountKeyRotationSecret`]
+        rotation_id: SecretVarId,
+    },
+    /// Created by the [`migrate_account`] invocation.
+    #[discriminant(16)]
+    PendingAccountMigration {
+        /// Address to migrate the account to
+        account: Address,
+        /// Address of the account to migrate
+        from: Address,
+        /// Identifier of secret-shared [`zk_compute::AccountMigrationSecret`]
+        migration_id: SecretVarId,
+    },
+    /// Created by the [`close_account`] invocation.
+    #[discriminant(17)]
+    PendingAccountClosure {
+        /// Account to close
+        account: Address,
+    },
+    /// Created by the [`reveal_my_balance`] invocation.
+    #[discriminant(18)]
+    PendingBalanceReveal {
+        /// Account to reveal the balance of
+        account: Address,
+    },
+}
+
+/// Decides who owns the secret balances of user and lottery accounts,
+/// see [`ContractState::variable_owner`]
+#[derive(ReadWriteRPC, ReadWriteState, CreateTypeSpec, PartialEq, Clone, Copy, Debug)]
+pub enum OwnershipPolicy {
+    /// Balances are owned by their real owner
+    #[discriminant(1)]
+    RealOwner {},
+
+    /// Balances are owned by the API address, for wallets unable to read secret variables
+    #[discriminant(2)]
+    Api {},
+
+    /// Balances are owned by the API address, unless their real owner opted in to owning them,
+    /// see [`set_real_ownership`]
+    #[discriminant(3)]
+    PerUser {},
+}
+
+/// Entry points paused by the admin, see [`set_pause_flags`]. Withdrawals and claims are never
+/// paused, so users can always exit.
+#[derive(ReadWriteRPC, ReadWriteState, CreateTypeSpec, Default, Clone, Debug)]
+pub struct PauseFlags {
+    /// Pauses depositing tokens, see [`purchase_credits`] and [`create_account_with_deposit`]
+    pub deposits: bool,
+
+    /// Pauses purchasing tickets, with credits, tokens or vouchers
+    pub purchases: bool,
+
+    /// Pauses creating accounts and lotteries
+    pub creation: bool,
+
+    /// Pauses drawing winners, see [`draw_winner`]
+    pub draws: bool,
 }
 
 #[derive(Debug)]
@@ -203,9 +425,26 @@ pub struct ContractState {
     /// API Address that is able to read secret variables (workaround for lack of support for reading secret variables with current Parti Wallet)
     api: Address,
 
+    /// Address that deployed the contract, allowed to change the [`OwnershipPolicy`]
+    admin: Address,
+    /// Who owns the secret balances, switched by the admin as wallet support improves
+    ownership_policy: OwnershipPolicy,
+    // Addresses that opted in to owning their balances under the per-user policy
+    real_ownership_opt_ins: AvlTreeMap<Address, bool>,
+    /// Entry points paused by the admin, for instance while a bug in a computation is fixed
+    paused: PauseFlags,
+
     // Set of user accounts and their secret var IDs for tracking balances
     user_accounts: AvlTreeMap<Address, SecretVarId>,
-    ua_account_key_map: AvlTreeMap<u128, Address>,
+    // Secret-shared account keys of the user accounts, readable by their owners
+    account_keys: AvlTreeMap<Address, SecretVarId>,
+    // Addresses of migrated user accounts, and the address they were migrated to
+    migrated_accounts: AvlTreeMap<Address, Address>,
+    // Latest copies of user account balances, readable by their owners
+    balance_reveals: AvlTreeMap<Address, SecretVarId>,
+    // Updated referrer balance of the ticket purchase whose result has not been opened yet. It
+    // replaces the user account of the referrer once the result reveals the referrer
+    pending_referrer_balance_id: Option<SecretVarId>,
 
     // Set of lottery accounts and their secret var IDs for tracking balances
     lottery_accounts: AvlTreeMap<LotteryId, SecretVarId>,
@@ -215,24 +454,37 @@ pub struct ContractState {
     pub work_queue: VecDeque<WorkListItem>,
     // Redundant variables that can be cleaned up
     redundant_variables: Vec<SecretVarId>,
+
+    /// Bounded journal of lifecycle events, for indexers to follow the contract activity
+    journal: Journal,
 }
 
 impl ContractState {
     /// Create a new contract state (used in `initialize`)
-    pub fn new(token: Address, api: Address) -> Self {
+    pub fn new(token: Address, api: Address, admin: Address) -> Self {
         ContractState {
             token,
 
             api,
 
+            admin,
+            ownership_policy: OwnershipPolicy::Api {},
+            real_ownership_opt_ins: AvlTreeMap::new(),
+            paused: PauseFlags::default(),
+
             user_accounts: AvlTreeMap::new(),
-            ua_account_key_map: AvlTreeMap::new(),
+            account_keys: AvlTreeMap::new(),
+            migrated_accounts: AvlTreeMap::new(),
+            balance_reveals: AvlTreeMap::new(),
+            pending_referrer_balance_id: None,
 
             lottery_accounts: AvlTreeMap::new(),
             lotteries: AvlTreeMap::new(),
 
             work_queue: VecDeque::new(),
             redundant_variables: vec![],
+
+            journal: Journal::default(),
         }
     }
 
@@ -252,7 +504,7 @@ impl ContractState {
     ) {
         //! This function is designed to transfer ownership to the correct owner
         //! but because Parti Wallet doesn't allow retreiving private keys and triggers 4 modals when trying to fetch each balance
-        //! We use an API address to read the secret variables as a workaround until this is supported
+        //! the [`OwnershipPolicy`] may hand the variables to an API address as a workaround until this is supported
 
         let mut previous_variable_ids = vec![];
 
@@ -271,8 +523,10 @@ impl ContractState {
                     self.add_user_account(owner, variable.variable_id);
                 }
 
-                VariableKind::LotteryAccount { owner, lottery_id } => {
-                    _owner = Some(owner.clone());
+                VariableKind::LotteryAccount { lottery_id, .. } => {
+                    // The metadata owner may be a purchaser, but the balance belongs to the creator
+                    let creator = self.get_lottery(&lottery_id).unwrap().creator;
+                    _owner = Some(self.resolve_account(&creator));
 
                     if let Some(previous_variable_id) = self.lottery_accounts.get(&lottery_id) {
                         previous_variable_ids.push(previous_variable_id)
@@ -287,8 +541,7 @@ impl ContractState {
                 // If the variable has an owner, transfer it to the owner
                 zk_state_change.push(ZkStateChange::TransferVariable {
                     variable: variable.variable_id,
-                    // new_owner: _owner.unwrap(),
-                    new_owner: self.api, // ! See comment at start of function explaining this
+                    new_owner: self.variable_owner(&_owner.unwrap()), // ! See comment at start of function explaining this
                 });
             }
         }
@@ -298,6 +551,38 @@ impl ContractState {
         })
     }
 
+    /// Replace the user account of the referrer credited by a ticket purchase with the updated
+    /// balance `referrer_balance_id`, see [`VariableKind::ReferrerAccount`]. The referrer is found
+    /// by `credited_raw_id`, the raw ID of its previous balance, which is `0` if no referrer was
+    /// credited. The updated balance is then empty, and deleted.
+    pub fn replace_referrer_account(
+        &mut self,
+        referrer_balance_id: SecretVarId,
+        credited_raw_id: u32,
+        zk_state_change: &mut Vec<ZkStateChange>,
+    ) {
+        let referrer = self
+            .user_accounts
+            .iter()
+            .find(|(_, variable_id)| credited_raw_id != 0 && variable_id.raw_id == credited_raw_id);
+
+        let Some((referrer, previous_variable_id)) = referrer else {
+            zk_state_change.push(ZkStateChange::DeleteVariables {
+                variables_to_delete: vec![referrer_balance_id],
+            });
+            return;
+        };
+
+        self.add_user_account(referrer, referrer_balance_id);
+        zk_state_change.push(ZkStateChange::TransferVariable {
+            variable: referrer_balance_id,
+            new_owner: self.variable_owner(&referrer),
+        });
+        zk_state_change.push(ZkStateChange::DeleteVariables {
+            variables_to_delete: vec![previous_variable_id],
+        });
+    }
+
     /// Should only be called from `zk_on_compute_complete` invocations.
     ///
     /// It is not possible to run [`ContractState::attempt_to_start_next_in_queue`] in the same event as
@@ -344,6 +629,11 @@ impl ContractState {
             return;
         }
 
+        // The user account of a credited referrer is outdated until the purchase result is opened
+        if self.pending_referrer_balance_id.is_some() {
+            return;
+        }
+
         let Some(worklist_item) = self.work_queue.pop_front() else {
             return;
         };
@@ -352,15 +642,10 @@ impl ContractState {
         match worklist_item {
             WorkListItem::PendingAccountCreation {
                 account,
-                account_key,
                 account_creation_id,
             } => {
-                if self.has_user_account(&account) {
-                    fail_safely(
-                        context,
-                        event_groups,
-                        "Cannot create new user when account already exists",
-                    );
+                if let Some(error_message) = self.account_creation_blocker(&account) {
+                    self.fail_work_item(context, event_groups, Some(account), None, error_message);
                     return self.attempt_to_start_next_in_queue(
                         context,
                         zk_state,
@@ -373,22 +658,173 @@ impl ContractState {
 
                 zk_state_change.push(zk_compute::create_account_start(
                     account_creation_id,
-                    account_key,
+                    account_creation_id.raw_id as u128,
                     Some(SHORTNAME_CREATE_ACCOUNT_COMPLETE),
                     [
                         &VariableKind::UserAccount { owner: account },
-                        &VariableKind::AccountCreationResult {
-                            owner: account,
-                            account_key,
-                        },
+                        &VariableKind::AccountCreationResult { owner: account },
+                        &VariableKind::AccountKey { owner: account },
+                    ],
+                ))
+            }
+            WorkListItem::PendingAccountCreationWithDeposit {
+                account,
+                amount,
+                account_creation_id,
+            } => {
+                self.redundant_variables.push(account_creation_id);
+
+                if let Some(error_message) = self.account_creation_blocker(&account) {
+                    // Tokens have already been received, so send them back
+                    self.transfer_tokens(event_groups, &account, amount);
+                    self.fail_work_item(context, event_groups, Some(account), None, error_message);
+                    return self.attempt_to_start_next_in_queue(
+                        context,
+                        zk_state,
+                        zk_state_change,
+                        event_groups,
+                    );
+                }
+
+                zk_state_change.push(zk_compute::create_account_with_deposit_start(
+                    account_creation_id,
+                    account_creation_id.raw_id as u128,
+                    amount,
+                    Some(SHORTNAME_CREATE_ACCOUNT_COMPLETE),
+                    [
+                        &VariableKind::UserAccount { owner: account },
+                        &VariableKind::AccountCreationResult { owner: account },
+                        &VariableKind::AccountKey { owner: account },
+                    ],
+                ))
+            }
+            WorkListItem::PendingAccountKeyRotation {
+                account,
+                rotation_id,
+            } => {
+                self.redundant_variables.push(rotation_id);
+
+                let error_message = if !self.has_user_account(&account) {
+                    Some("Cannot rotate the account key of an account that does not exist")
+                } else {
+                    self.account_key_rotation_blocker(&account)
+                };
+
+                if let Some(error_message) = error_message {
+                    self.fail_work_item(context, event_groups, Some(account), None, error_message);
+                    return self.attempt_to_start_next_in_queue(
+                        context,
+                        zk_state,
+                        zk_state_change,
+                        event_groups,
+                    );
+                }
+
+                zk_state_change.push(zk_compute::rotate_account_key_start(
+                    rotation_id,
+                    self.get_user_account_var_id(&account).unwrap(),
+                    rotation_id.raw_id as u128,
+                    Some(SHORTNAME_CREATE_ACCOUNT_COMPLETE),
+                    [
+                        &VariableKind::UserAccount { owner: account },
+                        &VariableKind::AccountKeyRotationResult { owner: account },
+                        &VariableKind::AccountKey { owner: account },
                     ],
                 ))
             }
+            WorkListItem::PendingAccountMigration {
+                account,
+                from,
+                migration_id,
+            } => {
+                self.redundant_variables.push(migration_id);
+
+                let error_message = if !self.has_user_account(&from) {
+                    Some("Cannot migrate an account that does not exist")
+                } else if self.has_user_account(&account) {
+                    Some("Cannot migrate an account to an address that already has an account")
+                } else if self.resolve_account(&account) != account
+                    && self.resolve_account(&account) != from
+                {
+                    // Only the account that left `account` may return to it
+                    Some("Cannot migrate an account to an address that was migrated elsewhere")
+                } else {
+                    None
+                };
+
+                if let Some(error_message) = error_message {
+                    self.fail_work_item(context, event_groups, Some(account), None, error_message);
+                    return self.attempt_to_start_next_in_queue(
+                        context,
+                        zk_state,
+                        zk_state_change,
+                        event_groups,
+                    );
+                }
+
+                zk_state_change.push(zk_compute::verify_account_migration_start(
+                    migration_id,
+                    self.get_user_account_var_id(&from).unwrap(),
+                    Some(SHORTNAME_ACCOUNT_MIGRATION_COMPLETE),
+                    &VariableKind::AccountMigrationResult {
+                        owner: account,
+                        from,
+                    },
+                ))
+            }
+            WorkListItem::PendingAccountClosure { account } => {
+                let error_message = if !self.has_user_account(&account) {
+                    Some("Cannot close an account that does not exist")
+                } else {
+                    self.account_closure_blocker(&account)
+                };
+
+                if let Some(error_message) = error_message {
+                    self.fail_work_item(context, event_groups, Some(account), None, error_message);
+                    return self.attempt_to_start_next_in_queue(
+                        context,
+                        zk_state,
+                        zk_state_change,
+                        event_groups,
+                    );
+                }
+
+                zk_state_change.push(zk_compute::burn_all_credits_start(
+                    self.get_user_account_var_id(&account).unwrap(),
+                    Some(SHORTNAME_ACCOUNT_CLOSURE_COMPLETE),
+                    &VariableKind::AccountClosureResult { owner: account },
+                ));
+            }
+            WorkListItem::PendingBalanceReveal { account } => {
+                if !self.has_user_account(&account) {
+                    self.fail_work_item(
+                        context,
+                        event_groups,
+                        Some(account),
+                        None,
+                        "Cannot reveal the balance of an account that does not exist",
+                    );
+                    return self.attempt_to_start_next_in_queue(
+                        context,
+                        zk_state,
+                        zk_state_change,
+                        event_groups,
+                    );
+                }
+
+                zk_state_change.push(zk_compute::reveal_balance_start(
+                    self.get_user_account_var_id(&account).unwrap(),
+                    Some(SHORTNAME_BALANCE_REVEAL_COMPLETE),
+                    &VariableKind::BalanceReveal { owner: account },
+                ));
+            }
             WorkListItem::PendingPurchaseCredits { account, credits } => {
                 if !self.has_user_account(&account) {
-                    fail_safely(
+                    self.fail_work_item(
                         context,
                         event_groups,
+                        Some(account),
+                        None,
                         "Cannot purchase credits for an account that does not exist",
                     );
                     return self.attempt_to_start_next_in_queue(
@@ -403,14 +839,19 @@ impl ContractState {
                     self.get_user_account_var_id(&account).unwrap(),
                     credits,
                     Some(SHORTNAME_MINT_COMPLETE),
-                    &VariableKind::UserAccount { owner: account },
+                    [
+                        &VariableKind::UserAccount { owner: account },
+                        &VariableKind::DepositResult { owner: account },
+                    ],
                 ));
             }
             WorkListItem::PendingRedeemCredits { account, credits } => {
                 if !self.has_user_account(&account) {
-                    fail_safely(
+                    self.fail_work_item(
                         context,
                         event_groups,
+                        Some(account),
+                        None,
                         "Cannot redeem credits for an account that does not exist",
                     );
                     return self.attempt_to_start_next_in_queue(
@@ -438,9 +879,11 @@ impl ContractState {
                 lottery_creation_id,
             } => {
                 if !self.has_user_account(&account) {
-                    fail_safely(
+                    self.fail_work_item(
                         context,
                         event_groups,
+                        Some(account),
+                        Some(lottery_id),
                         "Creator must have an account to create a lottery",
                     );
                     return self.attempt_to_start_next_in_queue(
@@ -451,11 +894,15 @@ impl ContractState {
                     );
                 }
 
-                self.redundant_variables.push(lottery_creation_id);
+                // Keep the creation secret around to verify access codes when purchasing tickets
+                let mut lstate = self.get_lottery(&lottery_id).unwrap();
+                lstate.creation_secret_id = Some(lottery_creation_id);
+                self.add_lottery(&lstate);
 
                 zk_state_change.push(zk_compute::create_lottery_start(
                     lottery_creation_id,
                     self.get_user_account_var_id(&account).unwrap(),
+                    lottery_id,
                     prize_pool,
                     Some(SHORTNAME_CREATE_LOTTERY_COMPLETE),
                     [
@@ -477,11 +924,25 @@ impl ContractState {
                 lottery_id,
                 ticket_purchase_id,
             } => {
-                if !self.has_user_account(&account) {
-                    fail_safely(
+                let lstate = self.get_lottery(&lottery_id).unwrap();
+
+                let error_message = if !self.has_user_account(&account) {
+                    Some("Cannot purchase lottery tickets for an account that does not exist")
+                } else if lstate.status != (LotteryStatus::Open {}) {
+                    Some("Cannot purchase lottery tickets for a lottery that is not open")
+                } else {
+                    None
+                };
+
+                if let Some(error_message) = error_message {
+                    // Discard the ticket, so it is not counted in the lottery statistics
+                    self.redundant_variables.push(ticket_purchase_id);
+                    self.fail_work_item(
                         context,
                         event_groups,
-                        "Cannot purchase lottery tickets for an account that does not exist",
+                        Some(account),
+                        Some(lottery_id),
+                        error_message,
                     );
                     return self.attempt_to_start_next_in_queue(
                         context,
@@ -491,17 +952,144 @@ impl ContractState {
                     );
                 }
 
+                let options = &lstate.options;
+                let [tier_1, tier_2, tier_3] = options.padded_discount_tiers(lstate.entry_cost);
+
+                if options.giveaway {
+                    zk_state_change.push(zk_compute::enter_giveaway_start(
+                        ticket_purchase_id,
+                        self.get_user_account_var_id(&account).unwrap(),
+                        lstate.secret_state_id.unwrap(),
+                        lstate.creation_secret_id.unwrap(),
+                        lstate.options.allow_creator_entry,
+                        Some(SHORTNAME_GIVEAWAY_ENTRY_COMPLETE),
+                        [
+                            &VariableKind::SecretLotteryStateData { lottery_id },
+                            &VariableKind::LotteryTicketPurchaseResult {
+                                owner: account,
+                                lottery_id,
+                                ticket_purchase_id,
+                            },
+                        ],
+                    ));
+                } else if options.referral_reward_percentage > 0 {
+                    // The referrer is only named in secret, so the referrer balance is found by
+                    // the computation, and revealed by the result
+                    zk_state_change.push(zk_compute::purchase_lottery_ticket_with_referral_start(
+                        ticket_purchase_id,
+                        self.get_user_account_var_id(&account).unwrap(),
+                        self.get_lottery_account_var_id(&lottery_id).unwrap(),
+                        lstate.secret_state_id.unwrap(),
+                        lstate.creation_secret_id.unwrap(),
+                        lstate.options.allow_creator_entry,
+                        lstate.entry_cost,
+                        lstate.max_tickets_per_purchase(),
+                        options.referral_reward_per_ticket(lstate.entry_cost),
+                        tier_1.min_tickets,
+                        tier_1.ticket_price,
+                        options.referral_reward_per_ticket(tier_1.ticket_price),
+                        tier_2.min_tickets,
+                        tier_2.ticket_price,
+                        options.referral_reward_per_ticket(tier_2.ticket_price),
+                        tier_3.min_tickets,
+                        tier_3.ticket_price,
+                        options.referral_reward_per_ticket(tier_3.ticket_price),
+                        Some(SHORTNAME_TICKET_PURCHASE_COMPLETE),
+                        [
+                            &VariableKind::UserAccount { owner: account },
+                            &VariableKind::LotteryAccount {
+                                owner: account,
+                                lottery_id,
+                            },
+                            &VariableKind::SecretLotteryStateData { lottery_id },
+                            &VariableKind::LotteryTicketPurchaseResult {
+                                owner: account,
+                                lottery_id,
+                                ticket_purchase_id,
+                            },
+                            &VariableKind::ReferrerAccount { lottery_id },
+                        ],
+                    ));
+                } else {
+                    zk_state_change.push(zk_compute::purchase_lottery_ticket_start(
+                        ticket_purchase_id,
+                        self.get_user_account_var_id(&account).unwrap(),
+                        self.get_lottery_account_var_id(&lottery_id).unwrap(),
+                        lstate.secret_state_id.unwrap(),
+                        lstate.creation_secret_id.unwrap(),
+                        lstate.options.allow_creator_entry,
+                        lstate.entry_cost,
+                        lstate.max_tickets_per_purchase(),
+                        tier_1.min_tickets,
+                        tier_1.ticket_price,
+                        tier_2.min_tickets,
+                        tier_2.ticket_price,
+                        tier_3.min_tickets,
+                        tier_3.ticket_price,
+                        Some(SHORTNAME_TICKET_PURCHASE_COMPLETE),
+                        [
+                            &VariableKind::UserAccount { owner: account },
+                            &VariableKind::LotteryAccount {
+                                owner: account,
+                                lottery_id,
+                            },
+                            &VariableKind::SecretLotteryStateData { lottery_id },
+                            &VariableKind::LotteryTicketPurchaseResult {
+                                owner: account,
+                                lottery_id,
+                                ticket_purchase_id,
+                            },
+                        ],
+                    ));
+                }
+            }
+            WorkListItem::PendingLotteryTicketDirectPurchase {
+                account,
+                lottery_id,
+                tickets,
+                cost,
+                ticket_purchase_id,
+            } => {
                 let lstate = self.get_lottery(&lottery_id).unwrap();
 
-                zk_state_change.push(zk_compute::purchase_lottery_ticket_start(
+                let error_message = if !self.has_user_account(&account) {
+                    Some("Cannot purchase lottery tickets for an account that does not exist")
+                } else if lstate.status != (LotteryStatus::Open {}) {
+                    Some("Cannot purchase lottery ticket for a lottery that is not open")
+                } else {
+                    None
+                };
+
+                if let Some(error_message) = error_message {
+                    // Tokens have already been received, so send them back
+                    self.redundant_variables.push(ticket_purchase_id);
+                    self.transfer_tokens(event_groups, &account, cost);
+                    self.fail_work_item(
+                        context,
+                        event_groups,
+                        Some(account),
+                        Some(lottery_id),
+                        error_message,
+                    );
+                    return self.attempt_to_start_next_in_queue(
+                        context,
+                        zk_state,
+                        zk_state_change,
+                        event_groups,
+                    );
+                }
+
+                zk_state_change.push(zk_compute::purchase_lottery_ticket_with_tokens_start(
                     ticket_purchase_id,
                     self.get_user_account_var_id(&account).unwrap(),
                     self.get_lottery_account_var_id(&lottery_id).unwrap(),
                     lstate.secret_state_id.unwrap(),
-                    lstate.entry_cost,
-                    Some(SHORTNAME_TICKET_PURCHASE_COMPLETE),
+                    lstate.creation_secret_id.unwrap(),
+                    lstate.options.allow_creator_entry,
+                    tickets,
+                    cost,
+                    Some(SHORTNAME_DIRECT_TICKET_PURCHASE_COMPLETE),
                     [
-                        &VariableKind::UserAccount { owner: account },
                         &VariableKind::LotteryAccount {
                             owner: account,
                             lottery_id,
@@ -510,28 +1098,39 @@ impl ContractState {
                         &VariableKind::LotteryTicketPurchaseResult {
                             owner: account,
                             lottery_id,
+                            ticket_purchase_id,
                         },
                     ],
                 ));
             }
             WorkListItem::PendingEntropyPublish { lottery_id } => {
-                let mut lstate = self.get_lottery(&lottery_id).unwrap();
+                let lstate = self.get_lottery(&lottery_id).unwrap();
 
                 match lstate.status {
                     LotteryStatus::Open {} => {
+                        let secret_state_id = lstate.secret_state_id.unwrap();
+
+                        // No more entries, so the access code and vouchers are no longer needed
+                        self.discard_lottery_entry_secrets(lottery_id);
+
                         // Mark lottery as closed
-                        lstate.status = LotteryStatus::Closed {};
-                        self.lotteries.insert(lottery_id, lstate.clone());
+                        self.mark_lottery_as_closed(
+                            lottery_id,
+                            LotteryTransition::at(context, secret_state_id),
+                        );
+                        self.record_event(context, JournalEvent::LotteryClosed { lottery_id });
 
                         // Open the secret state variable for the lottery
                         zk_state_change.push(ZkStateChange::OpenVariables {
-                            variables: vec![lstate.secret_state_id.unwrap()],
+                            variables: vec![secret_state_id],
                         });
                     }
                     _ => {
-                        fail_safely(
+                        self.fail_work_item(
                             context,
                             event_groups,
+                            None,
+                            Some(lottery_id),
                             "Cannot draw winner for a lottery that is not open",
                         );
                         return self.attempt_to_start_next_in_queue(
@@ -551,7 +1150,7 @@ impl ContractState {
 
                 match lstate.status {
                     LotteryStatus::Closed {} => {
-                        let creator = lstate.creator;
+                        let creator = self.resolve_account(&lstate.creator);
 
                         zk_state_change.push(zk_compute::draw_lottery_winner_start(
                             lstate.secret_state_id.unwrap(),
@@ -560,6 +1159,7 @@ impl ContractState {
                             lstate.entry_cost,
                             lstate.prize_pool,
                             winner_index,
+                            lstate.options.anonymous_winner,
                             Some(SHORTNAME_DRAW_WINNER_COMPLETE),
                             [
                                 &VariableKind::LotteryAccount {
@@ -570,13 +1170,16 @@ impl ContractState {
                                 &VariableKind::LotteryWinnerDrawResult {
                                     lottery_id: lottery_id,
                                 },
+                                &VariableKind::WinnerClaim { lottery_id },
                             ],
                         ));
                     }
                     _ => {
-                        fail_safely(
+                        self.fail_work_item(
                             context,
                             event_groups,
+                            None,
+                            Some(lottery_id),
                             "Cannot draw winner for a lottery that is not open",
                         );
                         return self.attempt_to_start_next_in_queue(
@@ -593,7 +1196,8 @@ impl ContractState {
 
                 match lstate.status {
                     LotteryStatus::Drawn {} => {
-                        let winner = lstate.winner.unwrap();
+                        // Unclaimed wins follow the account when it is migrated
+                        let winner = self.resolve_account(&lstate.winner.unwrap());
 
                         zk_state_change.push(zk_compute::claim_winnings_start(
                             self.get_lottery_account_var_id(&lottery_id).unwrap(),
@@ -609,9 +1213,11 @@ impl ContractState {
                         ));
                     }
                     _ => {
-                        fail_safely(
+                        self.fail_work_item(
                             context,
                             event_groups,
+                            None,
+                            Some(lottery_id),
                             "Cannot claim prize for a lottery that is not closed",
                         );
                         return self.attempt_to_start_next_in_queue(
@@ -623,37 +1229,425 @@ impl ContractState {
                     }
                 }
             }
-        };
-    }
+            WorkListItem::PendingAnonymousClaimPrize {
+                account,
+                lottery_id,
+                prize_claim_id,
+                payout_address,
+            } => {
+                let lstate = self.get_lottery(&lottery_id).unwrap();
+                self.redundant_variables.push(prize_claim_id);
 
-    /// Queues a new transfer, and possibly starts the associated computation, if there is nothing
-    /// else in the queue.
-    fn schedule_new_work_item(
-        &mut self,
-        context: &ContractContext,
-        zk_state: &ZkState<VariableKind>,
-        zk_state_change: &mut Vec<ZkStateChange>,
-        event_groups: &mut Vec<EventGroup>,
-        worklist_item: WorkListItem,
-    ) {
-        self.work_queue.push_back(worklist_item);
-        self.attempt_to_start_next_in_queue(context, zk_state, zk_state_change, event_groups)
-    }
+                // The payout does not touch any account, so the claimer does not need one
+                if lstate.status != (LotteryStatus::Drawn {}) {
+                    self.fail_work_item(
+                        context,
+                        event_groups,
+                        Some(account),
+                        Some(lottery_id),
+                        "Cannot claim prize for a lottery that is not drawn",
+                    );
+                    return self.attempt_to_start_next_in_queue(
+                        context,
+                        zk_state,
+                        zk_state_change,
+                        event_groups,
+                    );
+                }
 
-    /// Check if a user account exists
-    pub fn has_user_account(&self, address: &Address) -> bool {
-        self.user_accounts.contains_key(address)
-    }
+                zk_state_change.push(zk_compute::claim_winnings_to_payout_start(
+                    prize_claim_id,
+                    lstate.winner_claim_id.unwrap(),
+                    self.get_lottery_account_var_id(&lottery_id).unwrap(),
+                    Some(SHORTNAME_ANONYMOUS_CLAIM_COMPLETE),
+                    [
+                        &VariableKind::LotteryAccount {
+                            owner: lstate.creator,
+                            lottery_id,
+                        },
+                        &VariableKind::PrizeClaimResult {
+                            owner: account,
+                            lottery_id,
+                            payout_address,
+                        },
+                    ],
+                ));
+            }
+            WorkListItem::PendingLotteryStatistics { lottery_id } => {
+                let lstate = self.get_lottery(&lottery_id).unwrap();
+
+                match lstate.status {
+                    LotteryStatus::Drawn {} | LotteryStatus::Complete {} => {
+                        let [tier_1, tier_2, tier_3] =
+                            lstate.options.padded_discount_tiers(lstate.entry_cost);
+
+                        zk_state_change.push(zk_compute::compute_lottery_statistics_start(
+                            self.get_lottery_account_var_id(&lottery_id).unwrap(),
+                            lstate.entry_cost,
+                            lstate.max_tickets_per_purchase(),
+                            tier_1.min_tickets,
+                            tier_1.ticket_price,
+                            tier_2.min_tickets,
+                            tier_2.ticket_price,
+                            tier_3.min_tickets,
+                            tier_3.ticket_price,
+                            Some(SHORTNAME_LOTTERY_STATISTICS_COMPLETE),
+                            &VariableKind::LotteryStatisticsResult { lottery_id },
+                        ));
+                    }
+                    _ => {
+                        self.fail_work_item(
+                            context,
+                            event_groups,
+                            None,
+                            Some(lottery_id),
+                            "Cannot compute statistics for a lottery that has not been drawn",
+                        );
+                        return self.attempt_to_start_next_in_queue(
+                            context,
+                            zk_state,
+                            zk_state_change,
+                            event_groups,
+                        );
+                    }
+                }
+            }
+            WorkListItem::PendingVoucherRedemption {
+                account,
+                lottery_id,
+                ticket_purchase_id,
+            } => {
+                let lstate = self.get_lottery(&lottery_id).unwrap();
+
+                let error_message = if !self.has_user_account(&account) {
+                    Some("Cannot redeem a voucher for an account that does not exist")
+                } else if lstate.status != (LotteryStatus::Open {}) {
+                    Some("Cannot redeem a voucher for a lottery that is not open")
+                } else {
+                    None
+                };
+
+                if let Some(error_message) = error_message {
+                    self.redundant_variables.push(ticket_purchase_id);
+                    self.fail_work_item(
+                        context,
+                        event_groups,
+                        Some(account),
+                        Some(lottery_id),
+                        error_message,
+                    );
+                    return self.attempt_to_start_next_in_queue(
+                        context,
+                        zk_state,
+                        zk_state_change,
+                        event_groups,
+                    );
+                }
+
+                zk_state_change.push(zk_compute::redeem_voucher_start(
+                    ticket_purchase_id,
+                    self.get_user_account_var_id(&account).unwrap(),
+                    lstate.secret_state_id.unwrap(),
+                    lstate.creation_secret_id.unwrap(),
+                    lstate.options.allow_creator_entry,
+                    lstate.options.tickets_per_voucher,
+                    Some(SHORTNAME_VOUCHER_REDEMPTION_COMPLETE),
+                    [
+                        &VariableKind::SecretLotteryStateData { lottery_id },
+                        &VariableKind::LotteryTicketPurchaseResult {
+                            owner: account,
+                            lottery_id,
+                            ticket_purchase_id,
+                        },
+                        &VariableKind::SpentVoucher { lottery_id },
+                    ],
+                ));
+            }
+            WorkListItem::PendingSalesReveal { lottery_id } => {
+                let lstate = self.get_lottery(&lottery_id).unwrap();
+
+                match lstate.status {
+                    LotteryStatus::Open {} => {
+                        zk_state_change.push(zk_compute::reveal_sales_start(
+                            lstate.secret_state_id.unwrap(),
+                            self.get_lottery_account_var_id(&lottery_id).unwrap(),
+                            Some(SHORTNAME_SALES_REVEAL_COMPLETE),
+                            &VariableKind::SalesReport {
+                                owner: self.resolve_account(&lstate.creator),
+                                lottery_id,
+                            },
+                        ));
+                    }
+                    _ => {
+                        self.fail_work_item(
+                            context,
+                            event_groups,
+                            Some(lstate.creator),
+                            Some(lottery_id),
+                            "Cannot reveal sales for a lottery that is not open",
+                        );
+                        return self.attempt_to_start_next_in_queue(
+                            context,
+                            zk_state,
+                            zk_state_change,
+                            event_groups,
+                        );
+                    }
+                }
+            }
+        };
+    }
+
+    /// Queues a new transfer, and possibly starts the associated computation, if there is nothing
+    /// else in the queue.
+    fn schedule_new_work_item(
+        &mut self,
+        context: &ContractContext,
+        zk_state: &ZkState<VariableKind>,
+        zk_state_change: &mut Vec<ZkStateChange>,
+        event_groups: &mut Vec<EventGroup>,
+        worklist_item: WorkListItem,
+    ) {
+        self.work_queue.push_back(worklist_item);
+        self.attempt_to_start_next_in_queue(context, zk_state, zk_state_change, event_groups)
+    }
+
+    /// Append an event to the [`Journal`], timestamped with the current block production time
+    pub fn record_event(&mut self, context: &ContractContext, event: JournalEvent) {
+        self.journal.record(context.block_production_time, event);
+    }
+
+    /// Record a failed work item in the [`Journal`] and indicate the failure to the user using
+    /// [`fail_safely`].
+    pub fn fail_work_item(
+        &mut self,
+        context: &ContractContext,
+        event_groups: &mut Vec<EventGroup>,
+        account: Option<Address>,
+        lottery_id: Option<LotteryId>,
+        error_message: &str,
+    ) {
+        self.record_event(
+            context,
+            JournalEvent::WorkItemFailed {
+                account,
+                lottery_id,
+                error_message: String::from(error_message),
+            },
+        );
+        fail_safely(context, event_groups, error_message);
+    }
+
+    /// Transfer `amount` tokens held by the contract to `recipient`
+    pub fn transfer_tokens(
+        &self,
+        event_groups: &mut Vec<EventGroup>,
+        recipient: &Address,
+        amount: u128,
+    ) {
+        let mut event_group = EventGroup::builder();
+        MPC20Contract::at_address(self.token).transfer(&mut event_group, recipient, amount);
+        event_groups.push(event_group.build());
+    }
+
+    /// Check if a user account exists
+    pub fn has_user_account(&self, address: &Address) -> bool {
+        self.user_accounts.contains_key(address)
+    }
+
+    /// Reason that no user account can be created at `address`, if any. Migrated addresses keep
+    /// redirecting their tickets, wins and lotteries to the migrated account, so they stay unused
+    pub fn account_creation_blocker(&self, address: &Address) -> Option<&'static str> {
+        if self.has_user_account(address) {
+            Some("Cannot create new user when account already exists")
+        } else if self.migrated_accounts.contains_key(address) {
+            Some("Cannot create new user at an address whose account was migrated")
+        } else {
+            None
+        }
+    }
+
+    /// Address that the user account of `address` has been migrated to, following every
+    /// migration, see [`migrate_account`]. Produces `address` if it has not been migrated
+    pub fn resolve_account(&self, address: &Address) -> Address {
+        let mut account = *address;
+        while let Some(migrated_to) = self.migrated_accounts.get(&account) {
+            account = migrated_to;
+        }
+        account
+    }
+
+    /// Number of distinct accounts that purchased tickets for `lottery`. Purchasers that migrated
+    /// their account are counted once, under the account they migrated to
+    pub fn distinct_purchasers(&self, lottery: &LotteryState) -> u128 {
+        let purchasers: BTreeSet<Address> = lottery
+            .purchasers
+            .iter()
+            .map(|purchaser| self.resolve_account(purchaser))
+            .collect();
+        purchasers.len() as u128
+    }
+
+    /// Address that should own the secret balances of `owner`, according to the [`OwnershipPolicy`]
+    pub fn variable_owner(&self, owner: &Address) -> Address {
+        match self.ownership_policy {
+            OwnershipPolicy::RealOwner {} => *owner,
+            OwnershipPolicy::Api {} => self.api,
+            OwnershipPolicy::PerUser {} => {
+                if self.real_ownership_opt_ins.contains_key(owner) {
+                    *owner
+                } else {
+                    self.api
+                }
+            }
+        }
+    }
+
+    /// Transfer the user account balances, and the balances of the lotteries they created, to the
+    /// owner chosen by the [`OwnershipPolicy`]. Limited to the balances of `only`, if given
+    pub fn apply_ownership_policy(
+        &self,
+        only: Option<&Address>,
+        zk_state_change: &mut Vec<ZkStateChange>,
+    ) {
+        for (owner, variable_id) in self.user_accounts.iter() {
+            if only.is_none() || only == Some(&owner) {
+                zk_state_change.push(ZkStateChange::TransferVariable {
+                    variable: variable_id,
+                    new_owner: self.variable_owner(&owner),
+                });
+            }
+        }
+
+        for (lottery_id, variable_id) in self.lottery_accounts.iter() {
+            let creator = self.resolve_account(&self.get_lottery(&lottery_id).unwrap().creator);
+            if only.is_none() || only == Some(&creator) {
+                zk_state_change.push(ZkStateChange::TransferVariable {
+                    variable: variable_id,
+                    new_owner: self.variable_owner(&creator),
+                });
+            }
+        }
+    }
+
+    /// Move the user account and account key of `from` to `to`, leaving a redirect behind so that
+    /// tickets, wins and lotteries of `from` follow the account
+    pub fn migrate_user_account(
+        &mut self,
+        from: Address,
+        to: Address,
+        zk_state_change: &mut Vec<ZkStateChange>,
+    ) {
+        let balance_id = self.get_user_account_var_id(&from).unwrap();
+        self.user_accounts.remove(&from);
+        self.add_user_account(to, balance_id);
+
+        if let Some(account_key_id) = self.account_keys.get(&from) {
+            self.account_keys.remove(&from);
+            self.account_keys.insert(to, account_key_id);
+            zk_state_change.push(ZkStateChange::TransferVariable {
+                variable: account_key_id,
+                new_owner: to,
+            });
+        }
+
+        if let Some(balance_reveal_id) = self.balance_reveals.get(&from) {
+            self.balance_reveals.remove(&from);
+            self.balance_reveals.insert(to, balance_reveal_id);
+            zk_state_change.push(ZkStateChange::TransferVariable {
+                variable: balance_reveal_id,
+                new_owner: to,
+            });
+        }
+
+        // The opt-in follows the account, as the owner stays the same person
+        if self.real_ownership_opt_ins.contains_key(&from) {
+            self.real_ownership_opt_ins.remove(&from);
+            self.real_ownership_opt_ins.insert(to, true);
+        }
+
+        // `to` holds an account again, so it must not redirect anywhere
+        self.migrated_accounts.remove(&to);
+        self.migrated_accounts.insert(from, to);
+
+        self.apply_ownership_policy(Some(&to), zk_state_change);
+    }
+
+    /// Reason that the account key of `account` cannot be rotated, if any. Ticket purchases and
+    /// lottery creations keep the account key they were made with, which giveaways and creator
+    /// entry checks compare against until the lottery is drawn
+    pub fn account_key_rotation_blocker(&self, account: &Address) -> Option<&'static str> {
+        for (_, lottery) in self.lotteries.iter() {
+            if !matches!(
+                lottery.status,
+                LotteryStatus::Pending {} | LotteryStatus::Open {} | LotteryStatus::Closed {}
+            ) {
+                continue;
+            }
+
+            if self.resolve_account(&lottery.creator) == *account {
+                return Some(
+                    "Cannot rotate the account key with lotteries that have not been drawn",
+                );
+            }
+
+            let has_tickets = lottery
+                .purchasers
+                .iter()
+                .any(|purchaser| self.resolve_account(purchaser) == *account);
+            if has_tickets {
+                return Some(
+                    "Cannot rotate the account key with tickets in lotteries that have not been drawn",
+                );
+            }
+        }
+
+        None
+    }
+
+    /// Reason that the user account of `account` cannot be closed, if any. Winners must claim
+    /// their prizes first, and creators and purchasers must have their lotteries drawn first, as
+    /// any of their tickets may still win.
+    ///
+    /// Anonymous winners are not known, so they are not held back. Their prize stays claimable
+    /// with [`claim_to_payout_address`], which needs no account.
+    pub fn account_closure_blocker(&self, account: &Address) -> Option<&'static str> {
+        for (_, lottery) in self.lotteries.iter() {
+            let unclaimed_prize = lottery.status == (LotteryStatus::Drawn {})
+                && lottery.winner.map(|winner| self.resolve_account(&winner)) == Some(*account);
+            if unclaimed_prize {
+                return Some("Cannot close an account with unclaimed prizes");
+            }
+
+            let undrawn_lottery = matches!(
+                lottery.status,
+                LotteryStatus::Pending {} | LotteryStatus::Open {} | LotteryStatus::Closed {}
+            ) && self.resolve_account(&lottery.creator) == *account;
+            if undrawn_lottery {
+                return Some("Cannot close an account with lotteries that have not been drawn");
+            }
+
+            let undrawn_tickets = matches!(
+                lottery.status,
+                LotteryStatus::Pending {} | LotteryStatus::Open {} | LotteryStatus::Closed {}
+            ) && lottery
+                .purchasers
+                .iter()
+                .any(|purchaser| self.resolve_account(purchaser) == *account);
+            if undrawn_tickets {
+                return Some(
+                    "Cannot close an account with tickets in lotteries that have not been drawn",
+                );
+            }
+        }
+        None
+    }
 
     /// Add a new user account with its secret var ID
     pub fn add_user_account(&mut self, address: Address, secret_var_id: SecretVarId) {
         self.user_accounts.insert(address, secret_var_id);
     }
 
-    pub fn add_user_account_id(&mut self, address: Address, account_key: u128) {
-        self.ua_account_key_map.insert(account_key, address);
-    }
-
     /// Get the secret var ID for a user account
     pub fn get_user_account_var_id(&self, address: &Address) -> Option<SecretVarId> {
         self.user_accounts.get(address)
@@ -685,27 +1679,57 @@ impl ContractState {
     }
 
     /// Mark a lottery as open (once payment has been received to the contract)
-    pub fn mark_lottery_as_open(&mut self, lottery_id: LotteryId) {
+    pub fn mark_lottery_as_open(&mut self, lottery_id: LotteryId, transition: LotteryTransition) {
         let mut lottery = self.get_lottery(&lottery_id).unwrap().clone();
 
         lottery.status = LotteryStatus::Open {};
+        lottery.opened = Some(transition);
+
+        self.lotteries.insert(lottery_id, lottery);
+    }
+
+    /// Mark a lottery as closed, once the secret state is opened to pick the winner
+    pub fn mark_lottery_as_closed(&mut self, lottery_id: LotteryId, transition: LotteryTransition) {
+        let mut lottery = self.get_lottery(&lottery_id).unwrap().clone();
+
+        lottery.status = LotteryStatus::Closed {};
+        lottery.closed = Some(transition);
 
         self.lotteries.insert(lottery_id, lottery);
     }
 
-    pub fn mark_lottery_as_drawn(&mut self, lottery_id: LotteryId, winner: Address) {
+    /// Mark a lottery as drawn. Without tickets sold there is nothing to claim, so the lottery is
+    /// marked as complete straight away. An anonymous winner is never set
+    pub fn mark_lottery_as_drawn(
+        &mut self,
+        lottery_id: LotteryId,
+        tickets_sold: bool,
+        winner: Option<Address>,
+        transition: LotteryTransition,
+    ) {
         let mut lottery = self.get_lottery(&lottery_id).unwrap().clone();
 
-        lottery.status = LotteryStatus::Drawn {};
-        lottery.winner = Some(winner);
+        lottery.status = if tickets_sold {
+            LotteryStatus::Drawn {}
+        } else {
+            LotteryStatus::Complete {}
+        };
+        lottery.winner = winner;
+        lottery.drawn = Some(transition);
 
         self.lotteries.insert(lottery_id, lottery);
     }
 
-    pub fn mark_lottery_as_complete(&mut self, lottery_id: LotteryId) {
+    /// Mark a lottery as complete, once the winner has claimed their prize
+    pub fn mark_lottery_as_complete(
+        &mut self,
+        lottery_id: LotteryId,
+        transition: LotteryTransition,
+    ) {
         let mut lottery = self.get_lottery(&lottery_id).unwrap().clone();
 
         lottery.status = LotteryStatus::Complete {};
+        lottery.claimed = Some(transition);
 
         self.lotteries.insert(lottery_id, lottery);
     }
@@ -733,39 +1757,53 @@ impl ContractState {
         lottery.pending_secret_state_id = None;
         self.lotteries.insert(lottery_id, lottery);
     }
+
+    /// Mark the access code and vouchers of a lottery as redundant, once it no longer accepts
+    /// entries
+    pub fn discard_lottery_entry_secrets(&mut self, lottery_id: LotteryId) {
+        let mut lottery = self.get_lottery(&lottery_id).unwrap().clone();
+
+        if let Some(creation_secret_id) = lottery.creation_secret_id.take() {
+            self.redundant_variables.push(creation_secret_id);
+        }
+        self.redundant_variables.append(&mut lottery.voucher_ids);
+        self.lotteries.insert(lottery_id, lottery);
+    }
 }
 
 #[init(zk = true)]
 pub fn initialize(
-    _context: ContractContext,
+    context: ContractContext,
     _zk_state: ZkState<VariableKind>,
     token: Address,
     api: Address,
 ) -> ContractState {
-    ContractState::new(token, api)
+    ContractState::new(token, api, context.sender)
 }
 
 /**
  * Secret input
  *
- * User creates an account with an account key (generated client side and kept secret)
+ * User creates an account from secret randomness (generated client side and kept secret). The
+ * account key is derived in MPC and returned to the user as a secret variable, see
+ * [`zk_compute::AccountKeySecret`].
  */
 #[zk_on_secret_input(shortname = 0x40)]
 pub fn create_account(
     context: ContractContext,
     state: ContractState,
     _zk_state: ZkState<VariableKind>,
-    account_key: u128,
 ) -> (
     ContractState,
     Vec<EventGroup>,
     ZkInputDef<VariableKind, zk_compute::AccountCreationSecret>,
 ) {
+    assert!(!state.paused.creation, "Account creation is paused!");
+
     let input_def = ZkInputDef::with_metadata(
         Some(SHORTNAME_CREATE_ACCOUNT_INPUTTED),
         VariableKind::AccountCreationSecret {
             owner: context.sender,
-            account_key,
         },
     );
 
@@ -785,10 +1823,7 @@ pub fn create_account_inputted(
     let metadata = zk_state.get_variable(account_creation_id).unwrap();
 
     match metadata.metadata {
-        VariableKind::AccountCreationSecret {
-            owner: _,
-            account_key,
-        } => {
+        VariableKind::AccountCreationSecret { owner: _ } => {
             state.schedule_new_work_item(
                 &context,
                 &zk_state,
@@ -796,15 +1831,16 @@ pub fn create_account_inputted(
                 &mut event_groups,
                 WorkListItem::PendingAccountCreation {
                     account: zk_state.get_variable(account_creation_id).unwrap().owner,
-                    account_key,
                     account_creation_id,
                 },
             );
         }
         _ => {
-            fail_safely(
+            state.fail_work_item(
                 &context,
                 &mut event_groups,
+                None,
+                None,
                 "Invalid variable kind for account creation",
             );
         }
@@ -813,46 +1849,357 @@ pub fn create_account_inputted(
     (state, event_groups, zk_state_change)
 }
 
-/// Triggered on the completion of the computation for either of [`WorkListItem::PendingTransfer`],
-/// [`WorkListItem::PendingDeposit`] or [`WorkListItem::PendingAccountCreation`].
+/**
+ * Secret input
+ *
+ * User creates an account from secret randomness (generated client side and kept secret) and
+ * deposits `amount` tokens as the initial balance in the same flow
+ */
+#[zk_on_secret_input(shortname = 0x43)]
+pub fn create_account_with_deposit(
+    context: ContractContext,
+    state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    amount: u128,
+) -> (
+    ContractState,
+    Vec<EventGroup>,
+    ZkInputDef<VariableKind, zk_compute::AccountCreationSecret>,
+) {
+    assert!(!state.paused.creation, "Account creation is paused!");
+    assert!(!state.paused.deposits, "Deposits are paused!");
+
+    let input_def = ZkInputDef::with_metadata(
+        Some(SHORTNAME_CREATE_ACCOUNT_WITH_DEPOSIT_INPUTTED),
+        VariableKind::AccountCreationWithDepositSecret {
+            owner: context.sender,
+            amount,
+        },
+    );
+
+    (state, vec![], input_def)
+}
+
+/// Pulls the deposit for [`create_account_with_deposit`] from the owner of the secret input.
 ///
-/// Transfers ownership of the output variables to the owners defined by [`VariableKind::owner()`].
-#[zk_on_compute_complete(shortname = 0x61)]
-pub fn mint_complete(
+/// The account is only scheduled for creation once the tokens have been received, see
+/// [`account_deposit_callback`].
+#[zk_on_variable_inputted(shortname = 0x53)]
+pub fn create_account_with_deposit_inputted(
     context: ContractContext,
     mut state: ContractState,
     zk_state: ZkState<VariableKind>,
-    output_variables: Vec<SecretVarId>,
+    account_creation_id: SecretVarId,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
-    // Start next in queue
-    let mut zk_state_change = vec![];
+    let zk_state_change = vec![];
+    let mut event_groups = vec![];
 
-    // Move all variables to their expected owners
-    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
-    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    let metadata = zk_state.get_variable(account_creation_id).unwrap();
 
-    // Trigger [`continue_queue`]
-    let mut event_groups = vec![];
-    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+    match metadata.metadata {
+        VariableKind::AccountCreationWithDepositSecret { owner, amount } => {
+            let mut event_group = EventGroup::builder();
+            MPC20Contract::at_address(state.token).transfer_from(
+                &mut event_group,
+                &owner,
+                &context.contract_address,
+                amount,
+            );
+
+            event_group
+                .with_callback(SHORTNAME_ACCOUNT_DEPOSIT_CALLBACK)
+                .argument(account_creation_id)
+                .done();
+
+            event_groups.push(event_group.build());
+        }
+        _ => {
+            state.fail_work_item(
+                &context,
+                &mut event_groups,
+                None,
+                None,
+                "Invalid variable kind for account creation",
+            );
+        }
+    }
 
     (state, event_groups, zk_state_change)
 }
 
-#[zk_on_compute_complete(shortname = 0x66)]
-pub fn create_account_complete(
+/// Handles callback from [`create_account_with_deposit_inputted`].
+///
+/// If the transfer succeeded the account creation is queued, otherwise the secret input is
+/// discarded.
+#[callback(shortname = 0x1B, zk = true)]
+pub fn account_deposit_callback(
     context: ContractContext,
+    callback_context: CallbackContext,
     mut state: ContractState,
     zk_state: ZkState<VariableKind>,
-    output_variables: Vec<SecretVarId>,
+    account_creation_id: SecretVarId,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
-    let result_id: SecretVarId = *output_variables.get(1).unwrap();
-
-    // Start next in queue
     let mut zk_state_change = vec![];
     let mut event_groups = vec![];
 
-    // Move all variables to their expected owners
-    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
+    if !callback_context.success {
+        zk_state_change.push(ZkStateChange::DeleteVariables {
+            variables_to_delete: vec![account_creation_id],
+        });
+        state.fail_work_item(
+            &context,
+            &mut event_groups,
+            Some(zk_state.get_variable(account_creation_id).unwrap().owner),
+            None,
+            "Transfer did not succeed",
+        );
+        return (state, event_groups, zk_state_change);
+    }
+
+    match zk_state.get_variable(account_creation_id).unwrap().metadata {
+        VariableKind::AccountCreationWithDepositSecret { owner, amount } => {
+            state.schedule_new_work_item(
+                &context,
+                &zk_state,
+                &mut zk_state_change,
+                &mut event_groups,
+                WorkListItem::PendingAccountCreationWithDeposit {
+                    account: owner,
+                    amount,
+                    account_creation_id,
+                },
+            );
+        }
+        _ => panic!("Unexpected metadata type in account deposit callback!"),
+    }
+
+    (state, event_groups, zk_state_change)
+}
+
+/// Triggered on the completion of the computation for either of [`WorkListItem::PendingTransfer`],
+/// [`WorkListItem::PendingDeposit`] or [`WorkListItem::PendingAccountCreation`].
+///
+/// Transfers ownership of the output variables to the owners defined by [`VariableKind::owner()`].
+#[zk_on_compute_complete(shortname = 0x61)]
+pub fn mint_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id: SecretVarId = *output_variables.get(1).unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+
+    // Move all variables to their expected owners
+    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+
+    // Trigger [`continue_queue`]
+    let mut event_groups = vec![];
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
+    zk_state_change.push(ZkStateChange::OpenVariables {
+        variables: vec![result_id],
+    });
+
+    (state, event_groups, zk_state_change)
+}
+
+/// Triggered on the completion of account creation, and of account key rotation.
+///
+/// The account key replaces the previous account key of the owner, if any.
+#[zk_on_compute_complete(shortname = 0x66)]
+pub fn create_account_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id: SecretVarId = *output_variables.get(1).unwrap();
+    let account_key_id: SecretVarId = *output_variables.get(2).unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    match zk_state.get_variable(account_key_id).unwrap().metadata {
+        VariableKind::AccountKey { owner } => {
+            if let Some(previous_account_key_id) = state.account_keys.get(&owner) {
+                state.redundant_variables.push(previous_account_key_id);
+            }
+            state.account_keys.insert(owner, account_key_id);
+
+            // Unlike balances, the key goes to the owner rather than the API address, as it must
+            // only be readable by the owner
+            zk_state_change.push(ZkStateChange::TransferVariable {
+                variable: account_key_id,
+                new_owner: owner,
+            });
+        }
+        _ => panic!("Unexpected metadata type in create account!"),
+    }
+
+    // Move all variables to their expected owners
+    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
+    zk_state_change.push(ZkStateChange::OpenVariables {
+        variables: vec![result_id],
+    });
+
+    (state, event_groups, zk_state_change)
+}
+
+/**
+ * Secret input
+ *
+ * User replaces a leaked account key with a new one derived from fresh secret randomness, proving
+ * knowledge of the old account key. The balance moves to the new key. Refused while the account
+ * has tickets in or created lotteries that have not been drawn, as those keep the old account key,
+ * see [`ContractState::account_key_rotation_blocker`]. Anonymous prizes are claimed with a claim
+ * secret instead, so rotating after the draw does not affect them.
+ */
+#[zk_on_secret_input(shortname = 0x49)]
+pub fn rotate_account_key(
+    context: ContractContext,
+    state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+) -> (
+    ContractState,
+    Vec<EventGroup>,
+    ZkInputDef<VariableKind, zk_compute::AccountKeyRotationSecret>,
+) {
+    assert!(
+        state.has_user_account(&context.sender),
+        "Cannot rotate the account key of an account that does not exist!"
+    );
+
+    let input_def = ZkInputDef::with_metadata(
+        Some(SHORTNAME_ACCOUNT_KEY_ROTATION_INPUTTED),
+        VariableKind::AccountKeyRotationSecret {
+            owner: context.sender,
+        },
+    );
+
+    (state, vec![], input_def)
+}
+
+#[zk_on_variable_inputted(shortname = 0x58)]
+pub fn account_key_rotation_inputted(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    rotation_id: SecretVarId,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    match zk_state.get_variable(rotation_id).unwrap().metadata {
+        VariableKind::AccountKeyRotationSecret { owner } => {
+            state.schedule_new_work_item(
+                &context,
+                &zk_state,
+                &mut zk_state_change,
+                &mut event_groups,
+                WorkListItem::PendingAccountKeyRotation {
+                    account: owner,
+                    rotation_id,
+                },
+            );
+        }
+        _ => panic!("Unexpected metadata type in account key rotation!"),
+    }
+
+    (state, event_groups, zk_state_change)
+}
+
+/**
+ * Secret input
+ *
+ * Moves the user account of `from` to the sender, such as a new wallet replacing a lost one. The
+ * sender proves to hold the account key of the account. Outstanding tickets, unclaimed wins and
+ * lotteries created by `from` follow the account. No new account can be created at `from`
+ * afterwards, though the account may later migrate back to it.
+ */
+#[zk_on_secret_input(shortname = 0x4A)]
+pub fn migrate_account(
+    context: ContractContext,
+    state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    from: Address,
+) -> (
+    ContractState,
+    Vec<EventGroup>,
+    ZkInputDef<VariableKind, zk_compute::AccountMigrationSecret>,
+) {
+    assert!(
+        state.has_user_account(&from),
+        "Cannot migrate an account that does not exist!"
+    );
+    assert!(
+        !state.has_user_account(&context.sender),
+        "Cannot migrate an account to an address that already has an account!"
+    );
+
+    let input_def = ZkInputDef::with_metadata(
+        Some(SHORTNAME_ACCOUNT_MIGRATION_INPUTTED),
+        VariableKind::AccountMigrationSecret {
+            owner: context.sender,
+            from,
+        },
+    );
+
+    (state, vec![], input_def)
+}
+
+#[zk_on_variable_inputted(shortname = 0x59)]
+pub fn account_migration_inputted(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    migration_id: SecretVarId,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    match zk_state.get_variable(migration_id).unwrap().metadata {
+        VariableKind::AccountMigrationSecret { owner, from } => {
+            state.schedule_new_work_item(
+                &context,
+                &zk_state,
+                &mut zk_state_change,
+                &mut event_groups,
+                WorkListItem::PendingAccountMigration {
+                    account: owner,
+                    from,
+                    migration_id,
+                },
+            );
+        }
+        _ => panic!("Unexpected metadata type in account migration!"),
+    }
+
+    (state, event_groups, zk_state_change)
+}
+
+/// Opens the result of verifying the account key of an account migration. The account is moved
+/// once the result is opened, see [`ContractState::migrate_user_account`].
+#[zk_on_compute_complete(shortname = 0x6E)]
+pub fn account_migration_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id: SecretVarId = *output_variables.first().unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
     state.clean_up_redundant_secret_variables(&mut zk_state_change);
     trigger_continue_queue_if_needed(context, &state, &mut event_groups);
 
@@ -870,6 +2217,8 @@ pub fn purchase_credits(
     _zk_state: ZkState<VariableKind>,
     _credits: u128,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert!(!state.paused.deposits, "Deposits are paused!");
+
     let zk_state_change = vec![];
 
     let mut event_group = EventGroup::builder();
@@ -948,21 +2297,71 @@ pub fn redeem_credits(
     (state, event_groups, zk_state_change)
 }
 
-#[zk_on_compute_complete(shortname = 0x62)]
-pub fn withdraw_complete(
+/// Closes the user account of the sender, withdrawing the entire balance. Refused while the
+/// account has unclaimed prizes, see [`ContractState::account_closure_blocker`].
+#[action(shortname = 0x27, zk = true)]
+pub fn close_account(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert!(
+        state.has_user_account(&context.sender),
+        "Cannot close an account that does not exist!"
+    );
+    if let Some(blocker) = state.account_closure_blocker(&context.sender) {
+        panic!("{}!", blocker);
+    }
+
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    state.schedule_new_work_item(
+        &context,
+        &zk_state,
+        &mut zk_state_change,
+        &mut event_groups,
+        WorkListItem::PendingAccountClosure {
+            account: context.sender,
+        },
+    );
+
+    (state, event_groups, zk_state_change)
+}
+
+/// Removes the closed account, so no later work item can touch its balance, and opens the burned
+/// balance to withdraw it.
+#[zk_on_compute_complete(shortname = 0x6F)]
+pub fn account_closure_complete(
     context: ContractContext,
     mut state: ContractState,
     zk_state: ZkState<VariableKind>,
     output_variables: Vec<SecretVarId>,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
-    let result_id: SecretVarId = *output_variables.get(1).unwrap();
+    let result_id: SecretVarId = *output_variables.first().unwrap();
 
     // Start next in queue
     let mut zk_state_change = vec![];
     let mut event_groups = vec![];
 
-    // Move all variables to their expected owners
-    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
+    match zk_state.get_variable(result_id).unwrap().metadata {
+        VariableKind::AccountClosureResult { owner } => {
+            let balance_id = state.get_user_account_var_id(&owner).unwrap();
+            state.user_accounts.remove(&owner);
+            state.redundant_variables.push(balance_id);
+
+            if let Some(account_key_id) = state.account_keys.get(&owner) {
+                state.account_keys.remove(&owner);
+                state.redundant_variables.push(account_key_id);
+            }
+            if let Some(balance_reveal_id) = state.balance_reveals.get(&owner) {
+                state.balance_reveals.remove(&owner);
+                state.redundant_variables.push(balance_reveal_id);
+            }
+        }
+        _ => panic!("Unexpected metadata type in account closure!"),
+    }
+
     state.clean_up_redundant_secret_variables(&mut zk_state_change);
     trigger_continue_queue_if_needed(context, &state, &mut event_groups);
 
@@ -973,110 +2372,457 @@ pub fn withdraw_complete(
     (state, event_groups, zk_state_change)
 }
 
-#[zk_on_variables_opened]
-pub fn variable_opened(
+/// Reveals the balance of the sender to the sender only. Balances may be owned by the API address
+/// (see [`OwnershipPolicy`]), so this lets wallets read the balance without trusting the API.
+#[action(shortname = 0x28, zk = true)]
+pub fn reveal_my_balance(
     context: ContractContext,
     mut state: ContractState,
     zk_state: ZkState<VariableKind>,
-    opened_variables: Vec<SecretVarId>,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
-    // Determine result
-    let result_id: SecretVarId = *opened_variables.first().unwrap();
-    let result_variable = zk_state.get_variable(result_id).unwrap();
+    assert!(
+        state.has_user_account(&context.sender),
+        "Cannot reveal the balance of an account that does not exist!"
+    );
 
-    // Always remove result variable
     let mut zk_state_change = vec![];
-
     let mut event_groups = vec![];
 
-    match result_variable.metadata {
-        VariableKind::SecretLotteryStateData { lottery_id: _ } => {
-            // don't delete this variable, still needed
-        }
-        _ => {
-            // Delete the result variable, as it is no longer needed
-            zk_state_change.push(ZkStateChange::DeleteVariables {
-                variables_to_delete: vec![result_id],
-            });
-        }
-    }
+    state.schedule_new_work_item(
+        &context,
+        &zk_state,
+        &mut zk_state_change,
+        &mut event_groups,
+        WorkListItem::PendingBalanceReveal {
+            account: context.sender,
+        },
+    );
 
-    match result_variable.metadata {
-        VariableKind::AccountCreationResult { owner, account_key } => {
-            let result = read_result(&result_variable);
-            // Check that account creation was successful
-            if !result.successful {
-                fail_safely(&context, &mut event_groups, "Could not create user account");
-            } else {
-                // Add the user account to the state
-                state.add_user_account_id(owner, account_key)
-            }
-        }
-        VariableKind::WithdrawResult { owner: _ } => {
-            let result = read_result(&result_variable);
-            // Check that deposit was successful
-            if !result.successful {
-                fail_safely(
-                    &context,
-                    &mut event_groups,
-                    &format!(
-                        "Insufficient deposit balance! Could not withdraw {} tokens",
-                        result.amount
-                    ),
+    (state, event_groups, zk_state_change)
+}
+
+/// Switches the [`OwnershipPolicy`], transferring every existing balance to its new owner.
+/// Only callable by the admin.
+#[action(shortname = 0x29, zk = true)]
+pub fn set_ownership_policy(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    policy: OwnershipPolicy,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert!(
+        context.sender == state.admin,
+        "Only the admin can set the ownership policy!"
+    );
+
+    let mut zk_state_change = vec![];
+
+    state.ownership_policy = policy;
+    state.apply_ownership_policy(None, &mut zk_state_change);
+    state.record_event(&context, JournalEvent::OwnershipPolicyChanged { policy });
+
+    (state, vec![], zk_state_change)
+}
+
+/// Transfers the admin role to `new_admin`. Only callable by the admin.
+#[action(shortname = 0x2B, zk = true)]
+pub fn transfer_admin(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    new_admin: Address,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert!(
+        context.sender == state.admin,
+        "Only the admin can transfer the admin role!"
+    );
+
+    let previous_admin = state.admin;
+    state.admin = new_admin;
+    state.record_event(
+        &context,
+        JournalEvent::AdminTransferred {
+            from: previous_admin,
+            to: new_admin,
+        },
+    );
+
+    (state, vec![], vec![])
+}
+
+/// Rotates the API address, for instance when its key has been compromised. Balances owned by the
+/// previous API address are transferred to the new one. Only callable by the admin.
+#[action(shortname = 0x2C, zk = true)]
+pub fn set_api(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    api: Address,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert!(
+        context.sender == state.admin,
+        "Only the admin can set the API address!"
+    );
+
+    let mut zk_state_change = vec![];
+
+    let previous_api = state.api;
+    state.api = api;
+    state.apply_ownership_policy(None, &mut zk_state_change);
+    state.record_event(
+        &context,
+        JournalEvent::ApiRotated {
+            from: previous_api,
+            to: api,
+        },
+    );
+
+    (state, vec![], zk_state_change)
+}
+
+/// Replaces the token used for credits. Only callable by the admin, and only while no accounts
+/// exist, as the contract holds the tokens backing the balances.
+#[action(shortname = 0x2D, zk = true)]
+pub fn set_token(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    token: Address,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert!(
+        context.sender == state.admin,
+        "Only the admin can set the token!"
+    );
+    assert!(
+        state.user_accounts.is_empty() && state.lottery_accounts.is_empty(),
+        "Cannot set the token while accounts exist!"
+    );
+
+    let previous_token = state.token;
+    state.token = token;
+    state.record_event(
+        &context,
+        JournalEvent::TokenUpdated {
+            from: previous_token,
+            to: token,
+        },
+    );
+
+    (state, vec![], vec![])
+}
+
+/// Pauses or resumes entry points, for instance while a bug in a computation is fixed. Work items
+/// already queued still complete. Only callable by the admin.
+#[action(shortname = 0x2E, zk = true)]
+pub fn set_pause_flags(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    paused: PauseFlags,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert!(
+        context.sender == state.admin,
+        "Only the admin can pause the contract!"
+    );
+
+    state.paused = paused.clone();
+    state.record_event(&context, JournalEvent::PauseFlagsChanged { paused });
+
+    (state, vec![], vec![])
+}
+
+/// Opts the sender in to, or out of, owning their balances. Takes effect under the
+/// [`OwnershipPolicy::PerUser`] policy, and moves the balances out of reach of the API address.
+#[action(shortname = 0x2A, zk = true)]
+pub fn set_real_ownership(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    enabled: bool,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert!(
+        state.has_user_account(&context.sender),
+        "Cannot set the ownership of an account that does not exist!"
+    );
+
+    let mut zk_state_change = vec![];
+
+    if enabled {
+        state.real_ownership_opt_ins.insert(context.sender, true);
+    } else {
+        state.real_ownership_opt_ins.remove(&context.sender);
+    }
+    state.apply_ownership_policy(Some(&context.sender), &mut zk_state_change);
+
+    (state, vec![], zk_state_change)
+}
+
+/// Hands the copy of the balance to its owner, replacing any earlier copy.
+#[zk_on_compute_complete(shortname = 0x60)]
+pub fn balance_reveal_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let balance_reveal_id: SecretVarId = *output_variables.first().unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    match zk_state.get_variable(balance_reveal_id).unwrap().metadata {
+        VariableKind::BalanceReveal { owner } => {
+            // The previous copy is outdated
+            if let Some(previous_reveal_id) = state.balance_reveals.get(&owner) {
+                state.redundant_variables.push(previous_reveal_id);
+            }
+            state.balance_reveals.insert(owner, balance_reveal_id);
+
+            // Unlike balances, the copy goes to the owner rather than the API address, as it
+            // must only be readable by the owner
+            zk_state_change.push(ZkStateChange::TransferVariable {
+                variable: balance_reveal_id,
+                new_owner: owner,
+            });
+        }
+        _ => panic!("Unexpected metadata type in balance reveal!"),
+    }
+
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
+    (state, event_groups, zk_state_change)
+}
+
+#[zk_on_compute_complete(shortname = 0x62)]
+pub fn withdraw_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id: SecretVarId = *output_variables.get(1).unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    // Move all variables to their expected owners
+    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
+    zk_state_change.push(ZkStateChange::OpenVariables {
+        variables: vec![result_id],
+    });
+
+    (state, event_groups, zk_state_change)
+}
+
+#[zk_on_variables_opened]
+pub fn variable_opened(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    opened_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    // Determine result
+    let result_id: SecretVarId = *opened_variables.first().unwrap();
+    let result_variable = zk_state.get_variable(result_id).unwrap();
+
+    // Always remove result variable
+    let mut zk_state_change = vec![];
+
+    let mut event_groups = vec![];
+
+    match result_variable.metadata {
+        VariableKind::SecretLotteryStateData { lottery_id: _ } => {
+            // don't delete this variable, still needed
+        }
+        _ => {
+            // Delete the result variable, as it is no longer needed
+            zk_state_change.push(ZkStateChange::DeleteVariables {
+                variables_to_delete: vec![result_id],
+            });
+        }
+    }
+
+    match result_variable.metadata {
+        VariableKind::AccountCreationResult { owner } => {
+            let result = read_result(&result_variable);
+            // Account creation cannot fail, only the deposit of [`create_account_with_deposit`]
+            state.record_event(&context, JournalEvent::AccountCreated { account: owner });
+            if !result.successful {
+                state.transfer_tokens(&mut event_groups, &owner, result.amount);
+                state.fail_work_item(
+                    &context,
+                    &mut event_groups,
+                    Some(owner),
+                    None,
+                    &format!("Could not deposit {} tokens", result.amount),
+                );
+            }
+        }
+        VariableKind::AccountKeyRotationResult { owner } => {
+            let result = read_result(&result_variable);
+            // The account keeps its old key if the rotation failed
+            if !result.successful {
+                state.fail_work_item(
+                    &context,
+                    &mut event_groups,
+                    Some(owner),
+                    None,
+                    "Could not rotate account key, the old account key does not match",
                 );
+            }
+        }
+        VariableKind::AccountMigrationResult { owner, from } => {
+            let result = read_result(&result_variable);
+            let error_message = if !result.successful {
+                Some("Could not migrate account, the account key does not match")
+            } else if !state.has_user_account(&from) || state.has_user_account(&owner) {
+                // Accounts may have changed while the account key was being verified
+                Some("Could not migrate account, the accounts have changed")
             } else {
-                let recipient = result_variable.owner;
+                None
+            };
 
-                // Transfer the tokens from the contract to the recipient
-                let mut event_group = EventGroup::builder();
-                MPC20Contract::at_address(state.token).transfer(
-                    &mut event_group,
-                    &recipient,
-                    result.amount,
+            if let Some(error_message) = error_message {
+                state.fail_work_item(
+                    &context,
+                    &mut event_groups,
+                    Some(owner),
+                    None,
+                    error_message,
+                );
+            } else {
+                state.migrate_user_account(from, owner, &mut zk_state_change);
+                state.record_event(&context, JournalEvent::AccountMigrated { from, to: owner });
+            }
+        }
+        VariableKind::AccountClosureResult { owner } => {
+            let result = read_result(&result_variable);
+            // The account has already been removed, so return the burned balance
+            if result.amount > 0 {
+                state.transfer_tokens(&mut event_groups, &owner, result.amount);
+            }
+            state.record_event(&context, JournalEvent::AccountClosed { account: owner });
+        }
+        VariableKind::DepositResult { owner } => {
+            let result = read_result(&result_variable);
+            // Refund the deposit if the balance would overflow
+            if !result.successful {
+                state.transfer_tokens(&mut event_groups, &owner, result.amount);
+                state.fail_work_item(
+                    &context,
+                    &mut event_groups,
+                    Some(owner),
+                    None,
+                    &format!("Could not deposit {} tokens", result.amount),
+                );
+            }
+        }
+        VariableKind::WithdrawResult { owner } => {
+            let result = read_result(&result_variable);
+            // Check that deposit was successful
+            if !result.successful {
+                state.fail_work_item(
+                    &context,
+                    &mut event_groups,
+                    Some(owner),
+                    None,
+                    &format!(
+                        "Insufficient deposit balance! Could not withdraw {} tokens",
+                        result.amount
+                    ),
                 );
+            } else {
+                let recipient = result_variable.owner;
 
-                event_groups.push(event_group.build());
+                // Transfer the tokens from the contract to the recipient
+                state.transfer_tokens(&mut event_groups, &recipient, result.amount);
             }
         }
-        VariableKind::LotteryCreationResult {
-            owner: _,
-            lottery_id,
-        } => {
+        VariableKind::LotteryCreationResult { owner, lottery_id } => {
             let result = read_result(&result_variable);
             // Check that lottery creation was successful
             if !result.successful {
-                fail_safely(
+                state.fail_work_item(
                     &context,
                     &mut event_groups,
+                    Some(owner),
+                    Some(lottery_id),
                     &format!("Could not create lottery with ID {}", lottery_id),
                 );
+
+                state.discard_lottery_entry_secrets(lottery_id);
             } else {
                 // Update status of the lottery
-                state.mark_lottery_as_open(lottery_id);
+                state.mark_lottery_as_open(lottery_id, LotteryTransition::at(&context, result_id));
+                state.record_event(&context, JournalEvent::LotteryOpened { lottery_id });
 
                 // Promote the pending secret state ID to the lottery state ID
                 state.promote_lottery_pending_secret_state_id(lottery_id);
             }
         }
         VariableKind::LotteryTicketPurchaseResult {
-            owner: _,
+            owner,
             lottery_id,
+            ticket_purchase_id,
         } => {
-            let result = read_result(&result_variable);
+            // Purchases paying referral rewards also reveal the credited referrer balance
+            let referrer_balance_id = state.pending_referrer_balance_id.take();
+            let result = match referrer_balance_id {
+                Some(referrer_balance_id) => {
+                    let result = read_referral_purchase_result(&result_variable);
+                    state.replace_referrer_account(
+                        referrer_balance_id,
+                        result.referrer_balance_id,
+                        &mut zk_state_change,
+                    );
+                    zk_compute::ComputationResultPub {
+                        amount: result.amount,
+                        successful: result.successful,
+                    }
+                }
+                None => read_result(&result_variable),
+            };
             // Check that ticket purchase was successful
             if !result.successful {
-                fail_safely(
+                // Discard the ticket, so it is not counted when drawing the winner
+                zk_state_change.push(ZkStateChange::DeleteVariables {
+                    variables_to_delete: vec![ticket_purchase_id],
+                });
+                // Refund any tokens paid by [`purchase_tickets_with_tokens`]
+                if result.amount > 0 {
+                    state.transfer_tokens(&mut event_groups, &owner, result.amount);
+                }
+                state.fail_work_item(
                     &context,
                     &mut event_groups,
+                    Some(owner),
+                    Some(lottery_id),
                     &format!(
                         "Could not purchase lottery ticket for lottery with ID {}",
                         lottery_id
                     ),
                 );
             } else {
+                let mut lottery = state.get_lottery(&lottery_id).unwrap();
+                if !lottery.purchasers.contains(&owner) {
+                    lottery.purchasers.push(owner);
+                    state.add_lottery(&lottery);
+                }
+
                 // Promote the pending secret state ID to the lottery state ID
                 state.promote_lottery_pending_secret_state_id(lottery_id);
             }
+
+            if referrer_balance_id.is_some() {
+                // The queue waited for the referrer account to be replaced
+                trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+            }
         }
         VariableKind::SecretLotteryStateData { lottery_id } => {
             // This is a secret variable that contains the state of the lottery
@@ -1111,28 +2857,167 @@ pub fn variable_opened(
 
             // Check that the winner was drawn successfully
             if !result.successful {
+                // Nothing to claim, so the claim secret of an anonymous winner is not needed
+                let mut lottery = state.get_lottery(&lottery_id).unwrap();
+                if let Some(winner_claim_id) = lottery.winner_claim_id.take() {
+                    state.redundant_variables.push(winner_claim_id);
+                    state.add_lottery(&lottery);
+                }
+
                 // If draw was unsuccessful, this indicates the lottery had no tickets sold
-                state.mark_lottery_as_complete(lottery_id);
+                state.mark_lottery_as_drawn(
+                    lottery_id,
+                    false,
+                    None,
+                    LotteryTransition::at(&context, result_id),
+                );
+                state.record_event(
+                    &context,
+                    JournalEvent::LotteryDrawn {
+                        lottery_id,
+                        winner: None,
+                    },
+                );
+            } else if state
+                .get_lottery(&lottery_id)
+                .unwrap()
+                .options
+                .anonymous_winner
+            {
+                // The winning ticket has not been published, so the winner stays secret until
+                // they claim the prize
+                state.mark_lottery_as_drawn(
+                    lottery_id,
+                    true,
+                    None,
+                    LotteryTransition::at(&context, result_id),
+                );
+                state.record_event(
+                    &context,
+                    JournalEvent::LotteryDrawnAnonymously { lottery_id },
+                );
             } else {
-                // Find winner address (owner in the metadata of the user account)
-                // winner_id is the account ID from ZK data
-                let winner: Address = state.ua_account_key_map.get(&result.winner_id).unwrap();
+                // The winner is the owner of the winning ticket purchase, so account keys are
+                // never revealed
+                let winner: Address = state.resolve_account(
+                    &zk_state
+                        .get_variable(SecretVarId::new(result.winner_ticket_id))
+                        .unwrap()
+                        .owner,
+                );
 
-                state.mark_lottery_as_drawn(lottery_id, winner);
+                state.mark_lottery_as_drawn(
+                    lottery_id,
+                    true,
+                    Some(winner),
+                    LotteryTransition::at(&context, result_id),
+                );
+                state.record_event(
+                    &context,
+                    JournalEvent::LotteryDrawn {
+                        lottery_id,
+                        winner: Some(winner),
+                    },
+                );
+            }
+
+            if state
+                .get_lottery(&lottery_id)
+                .unwrap()
+                .options
+                .publish_statistics
+            {
+                state.schedule_new_work_item(
+                    &context,
+                    &zk_state,
+                    &mut zk_state_change,
+                    &mut event_groups,
+                    WorkListItem::PendingLotteryStatistics { lottery_id },
+                );
             }
         }
-        _ => {
-            fail_safely(&context, &mut event_groups, "unknown opened variable type");
-        }
-    }
+        VariableKind::PrizeClaimResult {
+            owner,
+            lottery_id,
+            payout_address,
+        } => {
+            let result = read_result(&result_variable);
+            // Check that the claimer proved to be the winner
+            if !result.successful {
+                state.fail_work_item(
+                    &context,
+                    &mut event_groups,
+                    Some(owner),
+                    Some(lottery_id),
+                    &format!("Could not claim prize of lottery with ID {}", lottery_id),
+                );
+            } else {
+                // The prize credits have been burned from the lottery account, so pay them out
+                state.transfer_tokens(&mut event_groups, &payout_address, result.amount);
 
-    (state, event_groups, zk_state_change)
-}
+                let mut lottery = state.get_lottery(&lottery_id).unwrap();
+                state
+                    .redundant_variables
+                    .extend(lottery.winner_claim_id.take());
+                state.add_lottery(&lottery);
 
-fn read_result(result_variable: &ZkClosed<VariableKind>) -> zk_compute::ComputationResultPub {
-    let result_bytes: &Vec<u8> = result_variable.data.as_ref().unwrap();
-    zk_compute::ComputationResultPub::secret_read_from(&mut result_bytes.as_slice())
-}
+                state.mark_lottery_as_complete(
+                    lottery_id,
+                    LotteryTransition::at(&context, result_id),
+                );
+                state.record_event(
+                    &context,
+                    JournalEvent::LotteryClaimedAnonymously { lottery_id },
+                );
+            }
+        }
+        VariableKind::LotteryStatisticsResult { lottery_id } => {
+            let statistics = read_lottery_statistics(&result_variable);
+
+            // Statistics that overflowed are not published
+            if !statistics.successful {
+                state.fail_work_item(
+                    &context,
+                    &mut event_groups,
+                    None,
+                    Some(lottery_id),
+                    "Could not compute lottery statistics, the statistics overflow",
+                );
+            } else {
+                let mut lottery = state.get_lottery(&lottery_id).unwrap();
+                lottery.statistics = Some(LotteryStatistics {
+                    tickets: statistics.tickets,
+                    purchasers: state.distinct_purchasers(&lottery),
+                    revenue: statistics.revenue,
+                });
+                state.add_lottery(&lottery);
+            }
+        }
+        _ => {
+            state.fail_work_item(
+                &context,
+                &mut event_groups,
+                None,
+                None,
+                "unknown opened variable type",
+            );
+        }
+    }
+
+    (state, event_groups, zk_state_change)
+}
+
+fn read_result(result_variable: &ZkClosed<VariableKind>) -> zk_compute::ComputationResultPub {
+    let result_bytes: &Vec<u8> = result_variable.data.as_ref().unwrap();
+    zk_compute::ComputationResultPub::secret_read_from(&mut result_bytes.as_slice())
+}
+
+fn read_referral_purchase_result(
+    result_variable: &ZkClosed<VariableKind>,
+) -> zk_compute::ReferralPurchaseResultPub {
+    let result_bytes: &Vec<u8> = result_variable.data.as_ref().unwrap();
+    zk_compute::ReferralPurchaseResultPub::secret_read_from(&mut result_bytes.as_slice())
+}
 
 fn read_secret_lottery_state(
     secret_variable: &ZkClosed<VariableKind>,
@@ -1146,6 +3031,13 @@ fn read_draw_result(result_variable: &ZkClosed<VariableKind>) -> zk_compute::Dra
     zk_compute::DrawResultPub::secret_read_from(&mut result_bytes.as_slice())
 }
 
+fn read_lottery_statistics(
+    result_variable: &ZkClosed<VariableKind>,
+) -> zk_compute::LotteryStatisticsPub {
+    let result_bytes: &Vec<u8> = result_variable.data.as_ref().unwrap();
+    zk_compute::LotteryStatisticsPub::secret_read_from(&mut result_bytes.as_slice())
+}
+
 /// Indicates failure to the user by spawning a new failing event.
 ///
 /// This is done to prevent stalling the queue, as individual [`WorkListItem`]s are that can fail, but it should
@@ -1236,11 +3128,53 @@ pub fn create_lottery(
     deadline: i64,
     entry_cost: u128,
     prize_pool: u128,
+    mut options: LotteryOptions,
 ) -> (
     ContractState,
     Vec<EventGroup>,
     ZkInputDef<VariableKind, zk_compute::LotteryCreationSecret>,
 ) {
+    assert!(!state.paused.creation, "Lottery creation is paused!");
+    // User account keys are tagged, so a lottery ID can never be used to probe for them
+    assert!(
+        lottery_id > 0 && lottery_id < zk_compute::USER_ACCOUNT_KEY_TAG,
+        "Lottery ID must be positive and below {}!",
+        zk_compute::USER_ACCOUNT_KEY_TAG
+    );
+    assert!(
+        options.referral_reward_percentage <= 100,
+        "Referral reward percentage cannot exceed 100!"
+    );
+    assert!(
+        options.discount_tiers.len() <= MAX_DISCOUNT_TIERS,
+        "Cannot have more than {} discount tiers!",
+        MAX_DISCOUNT_TIERS
+    );
+    assert!(
+        options
+            .discount_tiers
+            .windows(2)
+            .all(|tiers| tiers[0].min_tickets < tiers[1].min_tickets),
+        "Discount tiers must be in ascending order of minimum tickets!"
+    );
+    if options.giveaway {
+        assert!(
+            entry_cost == 0 && options.discount_tiers.is_empty() && options.voucher_count == 0,
+            "Giveaway lotteries must be free to enter, without discounts or vouchers!"
+        );
+    } else {
+        assert!(
+            entry_cost > 0,
+            "Entry cost must be positive, use a giveaway lottery for free entries!"
+        );
+    }
+
+    // Drop duplicate members, e.g. from a token holder snapshot
+    if let Some(allowlist) = options.allowlist.as_mut() {
+        let members = std::mem::take(&mut allowlist.members);
+        allowlist.add(members);
+    }
+
     let lstate = LotteryState {
         lottery_id,
         creator: context.sender,
@@ -1252,10 +3186,30 @@ pub fn create_lottery(
         secret_state_id: None,
         pending_secret_state_id: None,
         winner_index: None,
+        created_at: context.block_production_time,
+        opened: None,
+        closed: None,
+        drawn: None,
+        claimed: None,
+        options,
+        statistics: None,
+        sales_report_id: None,
+        creation_secret_id: None,
+        vouchers_registered: 0,
+        voucher_ids: vec![],
+        winner_claim_id: None,
+        purchasers: vec![],
     };
 
     // Add the lottery to the state
     state.add_lottery(&lstate);
+    state.record_event(
+        &context,
+        JournalEvent::LotteryCreated {
+            lottery_id,
+            creator: context.sender,
+        },
+    );
 
     let input_def = ZkInputDef::with_metadata(
         Some(SHORTNAME_CREATE_LOTTERY_INPUTTED),
@@ -1273,82 +3227,816 @@ pub fn create_lottery_inputted(
     context: ContractContext,
     mut state: ContractState,
     zk_state: ZkState<VariableKind>,
-    lottery_creation_id: SecretVarId,
+    lottery_creation_id: SecretVarId,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    let input_metadata = zk_state.get_variable(lottery_creation_id).unwrap();
+
+    match input_metadata.metadata {
+        VariableKind::LotteryCreationData {
+            creator,
+            lottery_id,
+        } => {
+            let lstate = state.get_lottery(&lottery_id).unwrap_or_else(|| {
+                panic!("Lottery with ID {} not found in state!", lottery_id);
+            });
+
+            state.schedule_new_work_item(
+                &context,
+                &zk_state,
+                &mut zk_state_change,
+                &mut event_groups,
+                WorkListItem::PendingLotteryCreation {
+                    account: creator,
+                    lottery_id,
+                    prize_pool: lstate.prize_pool,
+                    lottery_creation_id,
+                },
+            );
+        }
+        _ => panic!("Unexpected metadata type in create lottery!"),
+    }
+
+    (state, event_groups, zk_state_change)
+}
+
+#[zk_on_compute_complete(shortname = 0x63)]
+pub fn create_lottery_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id: SecretVarId = *output_variables.get(3).unwrap();
+    let secret_state_id: SecretVarId = *output_variables.get(2).unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    match zk_state.get_variable(secret_state_id).unwrap().metadata {
+        VariableKind::SecretLotteryStateData { lottery_id } => {
+            // Add the secret state ID to the lottery state
+            state.set_lottery_pending_secret_state_id(lottery_id, secret_state_id);
+        }
+        _ => panic!("Unexpected metadata type in create lottery!"),
+    }
+
+    // Move all variables to their expected owners
+    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
+    zk_state_change.push(ZkStateChange::OpenVariables {
+        variables: vec![result_id],
+    });
+
+    (state, event_groups, zk_state_change)
+}
+
+/// Asserts that `lottery` is a private lottery that can still be managed by the sender
+fn assert_can_manage_allowlist(
+    context: &ContractContext,
+    state: &ContractState,
+    lottery: &LotteryState,
+) {
+    assert!(
+        state.resolve_account(&lottery.creator) == context.sender,
+        "Only the creator of the lottery can manage its allowlist!"
+    );
+    assert!(
+        lottery.status == LotteryStatus::Pending {} || lottery.status == LotteryStatus::Open {},
+        "Lottery with ID {} is no longer accepting entries!",
+        lottery.lottery_id
+    );
+    assert!(
+        lottery.options.allowlist.is_some(),
+        "Lottery with ID {} has no allowlist!",
+        lottery.lottery_id
+    );
+}
+
+/**
+ * Creator allows `addresses` to purchase tickets in a private lottery
+ */
+#[action(shortname = 0x25, zk = true)]
+pub fn add_to_allowlist(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    lottery_id: LotteryId,
+    addresses: Vec<Address>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let mut lottery = state.get_lottery(&lottery_id).unwrap_or_else(|| {
+        panic!("Lottery with ID {} not found in state!", lottery_id);
+    });
+    assert_can_manage_allowlist(&context, &state, &lottery);
+
+    lottery.options.allowlist.as_mut().unwrap().add(addresses);
+    state.add_lottery(&lottery);
+
+    (state, vec![], vec![])
+}
+
+/**
+ * Creator stops `addresses` from purchasing tickets in a private lottery.
+ * Tickets already purchased are kept.
+ */
+#[action(shortname = 0x26, zk = true)]
+pub fn remove_from_allowlist(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    lottery_id: LotteryId,
+    addresses: Vec<Address>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let mut lottery = state.get_lottery(&lottery_id).unwrap_or_else(|| {
+        panic!("Lottery with ID {} not found in state!", lottery_id);
+    });
+    assert_can_manage_allowlist(&context, &state, &lottery);
+
+    lottery
+        .options
+        .allowlist
+        .as_mut()
+        .unwrap()
+        .remove(&addresses);
+    state.add_lottery(&lottery);
+
+    (state, vec![], vec![])
+}
+
+/**
+ * Secret input
+ *
+ * User purchases tickets with credits. If the lottery pays referral rewards, the referrer is given
+ * by its account key in the secret input, and credited by the purchase. The purchase result
+ * reveals the credited balance, so that the user account of the referrer can be replaced.
+ */
+#[zk_on_secret_input(shortname = 0x42)]
+pub fn purchase_tickets(
+    context: ContractContext,
+    state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    lottery_id: LotteryId,
+) -> (
+    ContractState,
+    Vec<EventGroup>,
+    ZkInputDef<VariableKind, zk_compute::LotteryTicketPurchaseSecret>,
+) {
+    assert!(!state.paused.purchases, "Ticket purchases are paused!");
+
+    let lottery = state.get_lottery(&lottery_id).unwrap_or_else(|| {
+        panic!("Lottery with ID {} not found in state!", lottery_id);
+    });
+
+    // Stop purchasing tickets for a lottery that is not open
+    assert!(
+        lottery.status == LotteryStatus::Open {},
+        "Lottery with ID {} is not open!",
+        lottery_id
+    );
+    assert!(
+        lottery.is_allowed(&context.sender),
+        "Not on the allowlist of lottery with ID {}!",
+        lottery_id
+    );
+
+    let input_def = ZkInputDef::with_metadata(
+        Some(SHORTNAME_TICKET_PURCHASE_INPUTTED),
+        VariableKind::LotteryTicketPurchaseSecretData {
+            owner: context.sender,
+            lottery_id,
+            direct_tickets: None,
+            voucher: false,
+        },
+    );
+
+    (state, vec![], input_def)
+}
+
+#[zk_on_variable_inputted(shortname = 0x52)]
+pub fn ticket_purchase_inputted(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    ticket_purchase_id: SecretVarId,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    let input_metadata = zk_state.get_variable(ticket_purchase_id).unwrap();
+
+    match input_metadata.metadata {
+        VariableKind::LotteryTicketPurchaseSecretData {
+            owner,
+            lottery_id,
+            direct_tickets: _,
+            voucher: _,
+        } => {
+            let lstate = state.get_lottery(&lottery_id).unwrap_or_else(|| {
+                panic!("Lottery with ID {} not found in state!", lottery_id);
+            });
+
+            match lstate.status {
+                LotteryStatus::Open {} => {
+                    state.schedule_new_work_item(
+                        &context,
+                        &zk_state,
+                        &mut zk_state_change,
+                        &mut event_groups,
+                        WorkListItem::PendingLotteryTicketPurchase {
+                            account: owner,
+                            lottery_id,
+                            ticket_purchase_id,
+                        },
+                    );
+                }
+                _ => {
+                    zk_state_change.push(ZkStateChange::DeleteVariables {
+                        variables_to_delete: vec![ticket_purchase_id],
+                    });
+                    state.fail_work_item(
+                        &context,
+                        &mut event_groups,
+                        Some(owner),
+                        Some(lottery_id),
+                        "Cannot purchase lottery ticket for a lottery that is not open",
+                    );
+                    return (state, event_groups, zk_state_change);
+                }
+            }
+        }
+        _ => panic!("Unexpected metadata type in create lottery!"),
+    }
+
+    (state, event_groups, zk_state_change)
+}
+
+#[zk_on_compute_complete(shortname = 0x64)]
+pub fn ticket_purchase_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id: SecretVarId = *output_variables.get(3).unwrap();
+    let secret_state_id: SecretVarId = *output_variables.get(2).unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    match zk_state.get_variable(secret_state_id).unwrap().metadata {
+        VariableKind::SecretLotteryStateData { lottery_id } => {
+            // Add the secret state ID to the lottery state
+            state.set_lottery_pending_secret_state_id(lottery_id, secret_state_id);
+        }
+        _ => panic!("Unexpected metadata type in create lottery!"),
+    }
+
+    // The referrer account is replaced once the result reveals the referrer, see
+    // [`ContractState::replace_referrer_account`]
+    if let Some(referrer_balance_id) = output_variables.get(4) {
+        state.pending_referrer_balance_id = Some(*referrer_balance_id);
+    }
+
+    // Move all variables to their expected owners
+    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
+    zk_state_change.push(ZkStateChange::OpenVariables {
+        variables: vec![result_id],
+    });
+
+    (state, event_groups, zk_state_change)
+}
+
+#[zk_on_compute_complete(shortname = 0x6C)]
+pub fn giveaway_entry_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let secret_state_id: SecretVarId = *output_variables.first().unwrap();
+    let result_id: SecretVarId = *output_variables.get(1).unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    match zk_state.get_variable(secret_state_id).unwrap().metadata {
+        VariableKind::SecretLotteryStateData { lottery_id } => {
+            // Add the secret state ID to the lottery state
+            state.set_lottery_pending_secret_state_id(lottery_id, secret_state_id);
+        }
+        _ => panic!("Unexpected metadata type in giveaway entry!"),
+    }
+
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
+    zk_state_change.push(ZkStateChange::OpenVariables {
+        variables: vec![result_id],
+    });
+
+    (state, event_groups, zk_state_change)
+}
+
+/**
+ * Secret input
+ *
+ * User purchases `tickets` tickets paying directly with tokens instead of credits. The user must
+ * have approved the contract to transfer the cost of the tickets, see [`LotteryState::ticket_cost`].
+ */
+#[zk_on_secret_input(shortname = 0x44)]
+pub fn purchase_tickets_with_tokens(
+    context: ContractContext,
+    state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    lottery_id: LotteryId,
+    tickets: u128,
+) -> (
+    ContractState,
+    Vec<EventGroup>,
+    ZkInputDef<VariableKind, zk_compute::LotteryTicketPurchaseSecret>,
+) {
+    assert!(!state.paused.purchases, "Ticket purchases are paused!");
+
+    let lottery = state.get_lottery(&lottery_id).unwrap_or_else(|| {
+        panic!("Lottery with ID {} not found in state!", lottery_id);
+    });
+
+    assert!(
+        lottery.status == LotteryStatus::Open {},
+        "Lottery with ID {} is not open!",
+        lottery_id
+    );
+    assert!(
+        lottery.is_allowed(&context.sender),
+        "Not on the allowlist of lottery with ID {}!",
+        lottery_id
+    );
+    assert!(
+        !lottery.options.giveaway,
+        "Giveaway lottery with ID {} cannot be entered with tokens!",
+        lottery_id
+    );
+    assert!(tickets > 0, "Must purchase at least one ticket!");
+    assert!(
+        lottery.ticket_cost(tickets).is_some(),
+        "Ticket cost overflows!"
+    );
+
+    let input_def = ZkInputDef::with_metadata(
+        Some(SHORTNAME_DIRECT_TICKET_PURCHASE_INPUTTED),
+        VariableKind::LotteryTicketPurchaseSecretData {
+            owner: context.sender,
+            lottery_id,
+            direct_tickets: Some(tickets),
+            voucher: false,
+        },
+    );
+
+    (state, vec![], input_def)
+}
+
+/// Pulls the payment for [`purchase_tickets_with_tokens`] from the owner of the secret input.
+///
+/// The purchase is only scheduled once the tokens have been received, see
+/// [`ticket_payment_callback`].
+#[zk_on_variable_inputted(shortname = 0x54)]
+pub fn direct_ticket_purchase_inputted(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    ticket_purchase_id: SecretVarId,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    let input_metadata = zk_state.get_variable(ticket_purchase_id).unwrap();
+
+    match input_metadata.metadata {
+        VariableKind::LotteryTicketPurchaseSecretData {
+            owner,
+            lottery_id,
+            direct_tickets: Some(tickets),
+            voucher: _,
+        } => {
+            let lstate = state.get_lottery(&lottery_id).unwrap_or_else(|| {
+                panic!("Lottery with ID {} not found in state!", lottery_id);
+            });
+
+            match lstate.status {
+                LotteryStatus::Open {} => {
+                    let mut event_group = EventGroup::builder();
+                    MPC20Contract::at_address(state.token).transfer_from(
+                        &mut event_group,
+                        &owner,
+                        &context.contract_address,
+                        lstate.ticket_cost(tickets).unwrap(),
+                    );
+
+                    event_group
+                        .with_callback(SHORTNAME_TICKET_PAYMENT_CALLBACK)
+                        .argument(ticket_purchase_id)
+                        .done();
+
+                    event_groups.push(event_group.build());
+                }
+                _ => {
+                    zk_state_change.push(ZkStateChange::DeleteVariables {
+                        variables_to_delete: vec![ticket_purchase_id],
+                    });
+                    state.fail_work_item(
+                        &context,
+                        &mut event_groups,
+                        Some(owner),
+                        Some(lottery_id),
+                        "Cannot purchase lottery ticket for a lottery that is not open",
+                    );
+                }
+            }
+        }
+        _ => panic!("Unexpected metadata type in direct ticket purchase!"),
+    }
+
+    (state, event_groups, zk_state_change)
+}
+
+/// Handles callback from [`direct_ticket_purchase_inputted`].
+///
+/// If the transfer succeeded the ticket purchase is queued, otherwise the secret input is
+/// discarded.
+#[callback(shortname = 0x1C, zk = true)]
+pub fn ticket_payment_callback(
+    context: ContractContext,
+    callback_context: CallbackContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    ticket_purchase_id: SecretVarId,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    if !callback_context.success {
+        zk_state_change.push(ZkStateChange::DeleteVariables {
+            variables_to_delete: vec![ticket_purchase_id],
+        });
+        state.fail_work_item(
+            &context,
+            &mut event_groups,
+            Some(zk_state.get_variable(ticket_purchase_id).unwrap().owner),
+            None,
+            "Transfer did not succeed",
+        );
+        return (state, event_groups, zk_state_change);
+    }
+
+    match zk_state.get_variable(ticket_purchase_id).unwrap().metadata {
+        VariableKind::LotteryTicketPurchaseSecretData {
+            owner,
+            lottery_id,
+            direct_tickets: Some(tickets),
+            voucher: _,
+        } => {
+            let lstate = state.get_lottery(&lottery_id).unwrap();
+
+            state.schedule_new_work_item(
+                &context,
+                &zk_state,
+                &mut zk_state_change,
+                &mut event_groups,
+                WorkListItem::PendingLotteryTicketDirectPurchase {
+                    account: owner,
+                    lottery_id,
+                    tickets,
+                    cost: lstate.ticket_cost(tickets).unwrap(),
+                    ticket_purchase_id,
+                },
+            );
+        }
+        _ => panic!("Unexpected metadata type in ticket payment callback!"),
+    }
+
+    (state, event_groups, zk_state_change)
+}
+
+#[zk_on_compute_complete(shortname = 0x68)]
+pub fn direct_ticket_purchase_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id: SecretVarId = *output_variables.get(2).unwrap();
+    let secret_state_id: SecretVarId = *output_variables.get(1).unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    match zk_state.get_variable(secret_state_id).unwrap().metadata {
+        VariableKind::SecretLotteryStateData { lottery_id } => {
+            // Add the secret state ID to the lottery state
+            state.set_lottery_pending_secret_state_id(lottery_id, secret_state_id);
+        }
+        _ => panic!("Unexpected metadata type in direct ticket purchase!"),
+    }
+
+    // Move all variables to their expected owners
+    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
+    zk_state_change.push(ZkStateChange::OpenVariables {
+        variables: vec![result_id],
+    });
+
+    (state, event_groups, zk_state_change)
+}
+
+/**
+ * Creator draws a winner for a lottery
+ */
+#[action(shortname = 0x22, zk = true)]
+pub fn draw_winner(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    lottery_id: LotteryId,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    assert!(!state.paused.draws, "Draws are paused!");
+
+    let lottery = state.get_lottery(&lottery_id).unwrap_or_else(|| {
+        panic!("Lottery with ID {} not found in state!", lottery_id);
+    });
+
+    assert!(
+        lottery.status == LotteryStatus::Open {},
+        "Lottery with ID {} is not open!",
+        lottery_id
+    );
+    assert!(
+        state.resolve_account(&lottery.creator) == context.sender,
+        "Only the creator of the lottery can draw a winner!"
+    );
+    assert!(
+        lottery.deadline < context.block_production_time,
+        "Cannot draw a winner before the lottery deadline!"
+    );
+
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    state.schedule_new_work_item(
+        &context,
+        &zk_state,
+        &mut zk_state_change,
+        &mut event_groups,
+        WorkListItem::PendingEntropyPublish { lottery_id },
+    );
+
+    (state, event_groups, zk_state_change)
+}
+
+#[zk_on_compute_complete(shortname = 0x65)]
+pub fn draw_winner_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id: SecretVarId = *output_variables.get(2).unwrap();
+    let winner_claim_id: SecretVarId = *output_variables.get(3).unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    match zk_state.get_variable(winner_claim_id).unwrap().metadata {
+        VariableKind::WinnerClaim { lottery_id } => {
+            // Only an anonymous winner needs to prove they hold the claim secret to claim
+            let mut lottery = state.get_lottery(&lottery_id).unwrap();
+            if lottery.options.anonymous_winner {
+                lottery.winner_claim_id = Some(winner_claim_id);
+                state.add_lottery(&lottery);
+            } else {
+                state.redundant_variables.push(winner_claim_id);
+            }
+        }
+        _ => panic!("Unexpected metadata type in draw winner complete!"),
+    }
+
+    // Move all variables to their expected owners
+    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
+    zk_state_change.push(ZkStateChange::OpenVariables {
+        variables: vec![result_id],
+    });
+
+    (state, event_groups, zk_state_change)
+}
+
+/// Opens the statistics computed for a lottery, see [`WorkListItem::PendingLotteryStatistics`].
+#[zk_on_compute_complete(shortname = 0x69)]
+pub fn lottery_statistics_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id: SecretVarId = *output_variables.first().unwrap();
+
+    // Start next in queue
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
+    zk_state_change.push(ZkStateChange::OpenVariables {
+        variables: vec![result_id],
+    });
+
+    (state, event_groups, zk_state_change)
+}
+
+/**
+ * Creator reveals the current sales of an open lottery to themselves, without revealing them to
+ * anyone else
+ */
+#[action(shortname = 0x24, zk = true)]
+pub fn reveal_sales_to_creator(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    lottery_id: LotteryId,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let lottery = state.get_lottery(&lottery_id).unwrap_or_else(|| {
+        panic!("Lottery with ID {} not found in state!", lottery_id);
+    });
+
+    assert!(
+        lottery.status == LotteryStatus::Open {},
+        "Lottery with ID {} is not open!",
+        lottery_id
+    );
+    assert!(
+        state.resolve_account(&lottery.creator) == context.sender,
+        "Only the creator of the lottery can reveal its sales!"
+    );
+
+    let mut zk_state_change = vec![];
+    let mut event_groups = vec![];
+
+    state.schedule_new_work_item(
+        &context,
+        &zk_state,
+        &mut zk_state_change,
+        &mut event_groups,
+        WorkListItem::PendingSalesReveal { lottery_id },
+    );
+
+    (state, event_groups, zk_state_change)
+}
+
+/// Hands the [`zk_compute::SalesReport`] to the creator, replacing any earlier report.
+#[zk_on_compute_complete(shortname = 0x6A)]
+pub fn sales_reveal_complete(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    output_variables: Vec<SecretVarId>,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let report_id: SecretVarId = *output_variables.first().unwrap();
+
+    // Start next in queue
     let mut zk_state_change = vec![];
     let mut event_groups = vec![];
 
-    let input_metadata = zk_state.get_variable(lottery_creation_id).unwrap();
+    match zk_state.get_variable(report_id).unwrap().metadata {
+        VariableKind::SalesReport { owner, lottery_id } => {
+            let mut lottery = state.get_lottery(&lottery_id).unwrap();
 
-    match input_metadata.metadata {
-        VariableKind::LotteryCreationData {
-            creator,
-            lottery_id,
-        } => {
-            let lstate = state.get_lottery(&lottery_id).unwrap_or_else(|| {
-                panic!("Lottery with ID {} not found in state!", lottery_id);
-            });
+            // The previous report is outdated
+            if let Some(previous_report_id) = lottery.sales_report_id {
+                state.redundant_variables.push(previous_report_id);
+            }
+            lottery.sales_report_id = Some(report_id);
+            state.add_lottery(&lottery);
 
-            state.schedule_new_work_item(
-                &context,
-                &zk_state,
-                &mut zk_state_change,
-                &mut event_groups,
-                WorkListItem::PendingLotteryCreation {
-                    account: creator,
-                    lottery_id,
-                    prize_pool: lstate.prize_pool,
-                    lottery_creation_id,
-                },
-            );
+            // Unlike balances, the report goes to the creator rather than the API address, as
+            // it must only be readable by the creator
+            zk_state_change.push(ZkStateChange::TransferVariable {
+                variable: report_id,
+                new_owner: owner,
+            });
         }
-        _ => panic!("Unexpected metadata type in create lottery!"),
+        _ => panic!("Unexpected metadata type in sales reveal!"),
     }
 
+    state.clean_up_redundant_secret_variables(&mut zk_state_change);
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
+
     (state, event_groups, zk_state_change)
 }
 
-#[zk_on_compute_complete(shortname = 0x63)]
-pub fn create_lottery_complete(
+/**
+ * Secret input
+ *
+ * Creator registers a secret voucher code for the lottery, up to
+ * [`LotteryOptions::voucher_count`] vouchers. Vouchers are free entries, so registering them
+ * costs the creator nothing.
+ */
+#[zk_on_secret_input(shortname = 0x45)]
+pub fn add_voucher(
     context: ContractContext,
     mut state: ContractState,
+    _zk_state: ZkState<VariableKind>,
+    lottery_id: LotteryId,
+) -> (
+    ContractState,
+    Vec<EventGroup>,
+    ZkInputDef<VariableKind, zk_compute::VoucherSecret>,
+) {
+    let mut lottery = state.get_lottery(&lottery_id).unwrap_or_else(|| {
+        panic!("Lottery with ID {} not found in state!", lottery_id);
+    });
+
+    assert!(
+        state.resolve_account(&lottery.creator) == context.sender,
+        "Only the creator of the lottery can add vouchers!"
+    );
+    assert!(
+        lottery.status == LotteryStatus::Pending {} || lottery.status == LotteryStatus::Open {},
+        "Lottery with ID {} is no longer accepting entries!",
+        lottery_id
+    );
+    assert!(
+        lottery.vouchers_registered < lottery.options.voucher_count,
+        "All {} vouchers of lottery with ID {} have been registered!",
+        lottery.options.voucher_count,
+        lottery_id
+    );
+
+    lottery.vouchers_registered += 1;
+    state.add_lottery(&lottery);
+
+    let input_def = ZkInputDef::with_metadata(
+        Some(SHORTNAME_VOUCHER_INPUTTED),
+        VariableKind::Voucher { lottery_id },
+    );
+
+    (state, vec![], input_def)
+}
+
+#[zk_on_variable_inputted(shortname = 0x55)]
+pub fn voucher_inputted(
+    _context: ContractContext,
+    mut state: ContractState,
     zk_state: ZkState<VariableKind>,
-    output_variables: Vec<SecretVarId>,
+    voucher_id: SecretVarId,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
-    let result_id: SecretVarId = *output_variables.get(3).unwrap();
-    let secret_state_id: SecretVarId = *output_variables.get(2).unwrap();
-
-    // Start next in queue
     let mut zk_state_change = vec![];
-    let mut event_groups = vec![];
 
-    match zk_state.get_variable(secret_state_id).unwrap().metadata {
-        VariableKind::SecretLotteryStateData { lottery_id } => {
-            // Add the secret state ID to the lottery state
-            state.set_lottery_pending_secret_state_id(lottery_id, secret_state_id);
+    match zk_state.get_variable(voucher_id).unwrap().metadata {
+        VariableKind::Voucher { lottery_id } => {
+            let mut lottery = state.get_lottery(&lottery_id).unwrap();
+            if matches!(
+                lottery.status,
+                LotteryStatus::Pending {} | LotteryStatus::Open {}
+            ) {
+                lottery.voucher_ids.push(voucher_id);
+                state.add_lottery(&lottery);
+            } else {
+                // Lottery closed while the voucher was being inputted, so it can never be redeemed
+                zk_state_change.push(ZkStateChange::DeleteVariables {
+                    variables_to_delete: vec![voucher_id],
+                });
+            }
         }
-        _ => panic!("Unexpected metadata type in create lottery!"),
+        _ => panic!("Unexpected metadata type in voucher inputted!"),
     }
 
-    // Move all variables to their expected owners
-    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
-    state.clean_up_redundant_secret_variables(&mut zk_state_change);
-    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
-
-    zk_state_change.push(ZkStateChange::OpenVariables {
-        variables: vec![result_id],
-    });
-
-    (state, event_groups, zk_state_change)
+    (state, vec![], zk_state_change)
 }
 
 /**
  * Secret input
  *
- * User creates an account with an account key (generated client side and kept secret)
+ * User redeems a voucher code for [`LotteryOptions::tickets_per_voucher`] free tickets. Neither the
+ * user nor the creator pays for the tickets, and no balance changes.
  */
-#[zk_on_secret_input(shortname = 0x42)]
-pub fn purchase_tickets(
+#[zk_on_secret_input(shortname = 0x46)]
+pub fn redeem_voucher(
     context: ContractContext,
     state: ContractState,
     _zk_state: ZkState<VariableKind>,
@@ -1358,30 +4046,38 @@ pub fn purchase_tickets(
     Vec<EventGroup>,
     ZkInputDef<VariableKind, zk_compute::LotteryTicketPurchaseSecret>,
 ) {
+    assert!(!state.paused.purchases, "Ticket purchases are paused!");
+
     let lottery = state.get_lottery(&lottery_id).unwrap_or_else(|| {
         panic!("Lottery with ID {} not found in state!", lottery_id);
     });
 
-    // Stop purchasing tickets for a lottery that is not open
     assert!(
         lottery.status == LotteryStatus::Open {},
         "Lottery with ID {} is not open!",
         lottery_id
     );
+    assert!(
+        lottery.is_allowed(&context.sender),
+        "Not on the allowlist of lottery with ID {}!",
+        lottery_id
+    );
 
     let input_def = ZkInputDef::with_metadata(
-        Some(SHORTNAME_TICKET_PURCHASE_INPUTTED),
+        Some(SHORTNAME_VOUCHER_REDEMPTION_INPUTTED),
         VariableKind::LotteryTicketPurchaseSecretData {
             owner: context.sender,
             lottery_id,
+            direct_tickets: None,
+            voucher: true,
         },
     );
 
     (state, vec![], input_def)
 }
 
-#[zk_on_variable_inputted(shortname = 0x52)]
-pub fn ticket_purchase_inputted(
+#[zk_on_variable_inputted(shortname = 0x56)]
+pub fn voucher_redemption_inputted(
     context: ContractContext,
     mut state: ContractState,
     zk_state: ZkState<VariableKind>,
@@ -1390,68 +4086,56 @@ pub fn ticket_purchase_inputted(
     let mut zk_state_change = vec![];
     let mut event_groups = vec![];
 
-    let input_metadata = zk_state.get_variable(ticket_purchase_id).unwrap();
-
-    match input_metadata.metadata {
-        VariableKind::LotteryTicketPurchaseSecretData { owner, lottery_id } => {
-            let lstate = state.get_lottery(&lottery_id).unwrap_or_else(|| {
-                panic!("Lottery with ID {} not found in state!", lottery_id);
-            });
-
-            match lstate.status {
-                LotteryStatus::Open {} => {
-                    state.schedule_new_work_item(
-                        &context,
-                        &zk_state,
-                        &mut zk_state_change,
-                        &mut event_groups,
-                        WorkListItem::PendingLotteryTicketPurchase {
-                            account: owner,
-                            lottery_id,
-                            ticket_purchase_id,
-                        },
-                    );
-                }
-                _ => {
-                    fail_safely(
-                        &context,
-                        &mut event_groups,
-                        "Cannot purchase lottery ticket for a lottery that is not open",
-                    );
-                    return (state, event_groups, zk_state_change);
-                }
-            }
+    match zk_state.get_variable(ticket_purchase_id).unwrap().metadata {
+        VariableKind::LotteryTicketPurchaseSecretData {
+            owner, lottery_id, ..
+        } => {
+            state.schedule_new_work_item(
+                &context,
+                &zk_state,
+                &mut zk_state_change,
+                &mut event_groups,
+                WorkListItem::PendingVoucherRedemption {
+                    account: owner,
+                    lottery_id,
+                    ticket_purchase_id,
+                },
+            );
         }
-        _ => panic!("Unexpected metadata type in create lottery!"),
+        _ => panic!("Unexpected metadata type in voucher redemption!"),
     }
 
     (state, event_groups, zk_state_change)
 }
 
-#[zk_on_compute_complete(shortname = 0x64)]
-pub fn ticket_purchase_complete(
+#[zk_on_compute_complete(shortname = 0x6B)]
+pub fn voucher_redemption_complete(
     context: ContractContext,
     mut state: ContractState,
     zk_state: ZkState<VariableKind>,
     output_variables: Vec<SecretVarId>,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
-    let result_id: SecretVarId = *output_variables.get(3).unwrap();
-    let secret_state_id: SecretVarId = *output_variables.get(2).unwrap();
+    let secret_state_id: SecretVarId = *output_variables.first().unwrap();
+    let result_id: SecretVarId = *output_variables.get(1).unwrap();
+    let spent_voucher_id: SecretVarId = *output_variables.get(2).unwrap();
 
     // Start next in queue
     let mut zk_state_change = vec![];
     let mut event_groups = vec![];
 
-    match zk_state.get_variable(secret_state_id).unwrap().metadata {
-        VariableKind::SecretLotteryStateData { lottery_id } => {
+    match zk_state.get_variable(spent_voucher_id).unwrap().metadata {
+        VariableKind::SpentVoucher { lottery_id } => {
             // Add the secret state ID to the lottery state
             state.set_lottery_pending_secret_state_id(lottery_id, secret_state_id);
+
+            // Track the spent voucher, so it is cleaned up with the other vouchers
+            let mut lottery = state.get_lottery(&lottery_id).unwrap();
+            lottery.voucher_ids.push(spent_voucher_id);
+            state.add_lottery(&lottery);
         }
-        _ => panic!("Unexpected metadata type in create lottery!"),
+        _ => panic!("Unexpected metadata type in voucher redemption!"),
     }
 
-    // Move all variables to their expected owners
-    state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
     state.clean_up_redundant_secret_variables(&mut zk_state_change);
     trigger_continue_queue_if_needed(context, &state, &mut event_groups);
 
@@ -1462,11 +4146,8 @@ pub fn ticket_purchase_complete(
     (state, event_groups, zk_state_change)
 }
 
-/**
- * Creator draws a winner for a lottery
- */
-#[action(shortname = 0x22, zk = true)]
-pub fn draw_winner(
+#[action(shortname = 0x23, zk = true)]
+pub fn claim(
     context: ContractContext,
     mut state: ContractState,
     zk_state: ZkState<VariableKind>,
@@ -1477,17 +4158,14 @@ pub fn draw_winner(
     });
 
     assert!(
-        lottery.status == LotteryStatus::Open {},
-        "Lottery with ID {} is not open!",
+        lottery.status == LotteryStatus::Drawn {},
+        "Lottery with ID {} is not drawn!",
         lottery_id
     );
     assert!(
-        lottery.creator == context.sender,
-        "Only the creator of the lottery can draw a winner!"
-    );
-    assert!(
-        lottery.deadline < context.block_production_time,
-        "Cannot draw a winner before the lottery deadline!"
+        !lottery.options.anonymous_winner,
+        "Lottery with ID {} has an anonymous winner, use claim_to_payout_address!",
+        lottery_id
     );
 
     let mut zk_state_change = vec![];
@@ -1498,44 +4176,85 @@ pub fn draw_winner(
         &zk_state,
         &mut zk_state_change,
         &mut event_groups,
-        WorkListItem::PendingEntropyPublish { lottery_id },
+        WorkListItem::PendingClaimPrize { lottery_id },
     );
 
     (state, event_groups, zk_state_change)
 }
 
-#[zk_on_compute_complete(shortname = 0x65)]
-pub fn draw_winner_complete(
+#[zk_on_compute_complete(shortname = 0x67)]
+pub fn claim_complete(
     context: ContractContext,
     mut state: ContractState,
     zk_state: ZkState<VariableKind>,
     output_variables: Vec<SecretVarId>,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
-    let result_id: SecretVarId = *output_variables.get(2).unwrap();
-
     // Start next in queue
     let mut zk_state_change = vec![];
     let mut event_groups = vec![];
 
+    // Get the lottery ID and winner from the metadata of the lottery account variable (index 1)
+    let lottery_account_id: SecretVarId = output_variables[1];
+    let (lottery_id, winner) = match zk_state.get_variable(lottery_account_id).unwrap().metadata {
+        VariableKind::LotteryAccount { owner, lottery_id } => (lottery_id, owner),
+        _ => panic!("Unexpected metadata type in claim complete!"),
+    };
+
     // Move all variables to their expected owners
     state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
     state.clean_up_redundant_secret_variables(&mut zk_state_change);
-    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
 
-    zk_state_change.push(ZkStateChange::OpenVariables {
-        variables: vec![result_id],
-    });
+    state.mark_lottery_as_complete(
+        lottery_id,
+        LotteryTransition::at(&context, lottery_account_id),
+    );
+    state.record_event(
+        &context,
+        JournalEvent::LotteryClaimed { lottery_id, winner },
+    );
+
+    trigger_continue_queue_if_needed(context, &state, &mut event_groups);
 
     (state, event_groups, zk_state_change)
 }
 
-#[action(shortname = 0x23, zk = true)]
-pub fn claim(
+/**
+ * Secret input
+ *
+ * Winner of a lottery with an anonymous winner claims the prize by providing the claim secret of
+ * the winning ticket purchase, see [`LotteryOptions::anonymous_winner`], and has it paid out in
+ * tokens to `payout_address`.
+ * Anyone can attempt a claim, but only the winner succeeds. No account is touched, so sending
+ * the claim from an address not linked to the winner (such as the payout address) keeps the win
+ * unlinkable to the account that bought the tickets.
+ */
+#[zk_on_secret_input(shortname = 0x48)]
+pub fn claim_to_payout_address(
     context: ContractContext,
-    mut state: ContractState,
-    zk_state: ZkState<VariableKind>,
+    state: ContractState,
+    _zk_state: ZkState<VariableKind>,
     lottery_id: LotteryId,
-) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    payout_address: Address,
+) -> (
+    ContractState,
+    Vec<EventGroup>,
+    ZkInputDef<VariableKind, zk_compute::PrizeClaimSecret>,
+) {
+    assert_can_claim_anonymously(&state, lottery_id);
+
+    let input_def = ZkInputDef::with_metadata(
+        Some(SHORTNAME_PRIZE_CLAIM_INPUTTED),
+        VariableKind::PrizeClaimSecretData {
+            owner: context.sender,
+            lottery_id,
+            payout_address,
+        },
+    );
+
+    (state, vec![], input_def)
+}
+
+fn assert_can_claim_anonymously(state: &ContractState, lottery_id: LotteryId) {
     let lottery = state.get_lottery(&lottery_id).unwrap_or_else(|| {
         panic!("Lottery with ID {} not found in state!", lottery_id);
     });
@@ -1545,48 +4264,69 @@ pub fn claim(
         "Lottery with ID {} is not drawn!",
         lottery_id
     );
+    assert!(
+        lottery.options.anonymous_winner,
+        "Lottery with ID {} does not have an anonymous winner, use claim!",
+        lottery_id
+    );
+}
 
+#[zk_on_variable_inputted(shortname = 0x57)]
+pub fn prize_claim_inputted(
+    context: ContractContext,
+    mut state: ContractState,
+    zk_state: ZkState<VariableKind>,
+    prize_claim_id: SecretVarId,
+) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
     let mut zk_state_change = vec![];
     let mut event_groups = vec![];
 
-    state.schedule_new_work_item(
-        &context,
-        &zk_state,
-        &mut zk_state_change,
-        &mut event_groups,
-        WorkListItem::PendingClaimPrize { lottery_id },
-    );
+    match zk_state.get_variable(prize_claim_id).unwrap().metadata {
+        VariableKind::PrizeClaimSecretData {
+            owner,
+            lottery_id,
+            payout_address,
+        } => {
+            state.schedule_new_work_item(
+                &context,
+                &zk_state,
+                &mut zk_state_change,
+                &mut event_groups,
+                WorkListItem::PendingAnonymousClaimPrize {
+                    account: owner,
+                    lottery_id,
+                    prize_claim_id,
+                    payout_address,
+                },
+            );
+        }
+        _ => panic!("Unexpected metadata type in prize claim!"),
+    }
 
     (state, event_groups, zk_state_change)
 }
 
-#[zk_on_compute_complete(shortname = 0x67)]
-pub fn claim_complete(
+#[zk_on_compute_complete(shortname = 0x6D)]
+pub fn anonymous_claim_complete(
     context: ContractContext,
     mut state: ContractState,
     zk_state: ZkState<VariableKind>,
     output_variables: Vec<SecretVarId>,
 ) -> (ContractState, Vec<EventGroup>, Vec<ZkStateChange>) {
+    let result_id: SecretVarId = *output_variables.get(1).unwrap();
+
     // Start next in queue
     let mut zk_state_change = vec![];
     let mut event_groups = vec![];
 
-    // Get the lottery ID from the metadata of the lottery account variable (index 1)
-    let lottery_id: LotteryId = match zk_state.get_variable(output_variables[1]).unwrap().metadata {
-        VariableKind::LotteryAccount {
-            owner: _,
-            lottery_id,
-        } => lottery_id,
-        _ => panic!("Unexpected metadata type in claim complete!"),
-    };
-
     // Move all variables to their expected owners
     state.transfer_variables_to_owner(&zk_state, output_variables, &mut zk_state_change);
     state.clean_up_redundant_secret_variables(&mut zk_state_change);
-
-    state.mark_lottery_as_complete(lottery_id);
-
     trigger_continue_queue_if_needed(context, &state, &mut event_groups);
 
+    zk_state_change.push(ZkStateChange::OpenVariables {
+        variables: vec![result_id],
+    });
+
     (state, event_groups, zk_state_change)
 }
@@ -3,9 +3,11 @@
 //! used throughout the contract.
 
 use create_type_spec_derive::CreateTypeSpec;
+use read_write_rpc_derive::ReadWriteRPC;
 use read_write_state_derive::ReadWriteState;
 
 use pbc_contract_common::address::Address;
+use pbc_contract_common::context::ContractContext;
 use pbc_contract_common::zk::SecretVarId;
 
 /// Unique identifier for lottery instances.
@@ -36,6 +38,157 @@ pub enum LotteryStatus {
     Complete {},
 }
 
+/// Maximum number of [`DiscountTier`]s of a lottery.
+/// Bounded, as every tier is passed to the ticket purchase computations.
+pub const MAX_DISCOUNT_TIERS: usize = 3;
+
+/// Reduced ticket price for purchases of at least `min_tickets` tickets.
+#[derive(ReadWriteRPC, ReadWriteState, CreateTypeSpec, Clone, Debug)]
+pub struct DiscountTier {
+    /// Minimum number of tickets in a single purchase for the tier to apply
+    pub min_tickets: u128,
+
+    /// Price per ticket when the tier applies
+    pub ticket_price: u128,
+}
+
+/// Addresses allowed to purchase tickets in a private lottery.
+#[derive(ReadWriteRPC, ReadWriteState, CreateTypeSpec, Clone, Debug)]
+pub struct Allowlist {
+    /// MPC20 token whose holders the members were initially taken from, if any.
+    ///
+    /// Contracts cannot read the balances of other contracts, so the holders are snapshotted
+    /// off-chain from the public state of the token when creating the lottery.
+    pub token: Option<Address>,
+
+    /// Addresses allowed to purchase tickets
+    pub members: Vec<Address>,
+}
+
+impl Allowlist {
+    /// Add members, ignoring addresses that are already members
+    pub fn add(&mut self, addresses: Vec<Address>) {
+        for address in addresses {
+            if !self.members.contains(&address) {
+                self.members.push(address);
+            }
+        }
+    }
+
+    /// Remove members, ignoring addresses that are not members
+    pub fn remove(&mut self, addresses: &[Address]) {
+        self.members.retain(|member| !addresses.contains(member));
+    }
+}
+
+/// Options chosen by the creator when creating a lottery.
+#[derive(ReadWriteRPC, ReadWriteState, CreateTypeSpec, Clone, Debug)]
+pub struct LotteryOptions {
+    /// Whether to publish [`LotteryStatistics`] once the lottery has been drawn
+    pub publish_statistics: bool,
+
+    /// Percentage (0-100) of the ticket price paid to the referrer of a ticket purchase. The
+    /// referrer is named in secret, and credited by the purchase, see [`crate::purchase_tickets`].
+    pub referral_reward_percentage: u8,
+
+    /// Bulk discounts, in ascending order of [`DiscountTier::min_tickets`].
+    /// The last tier that applies to a purchase sets the price of every ticket in the purchase.
+    pub discount_tiers: Vec<DiscountTier>,
+
+    /// Restricts ticket purchases to the members of the allowlist, if any.
+    /// Managed by the creator after creation, see [`crate::add_to_allowlist`].
+    pub allowlist: Option<Allowlist>,
+
+    /// Number of vouchers the creator may register, see [`crate::add_voucher`]
+    pub voucher_count: u32,
+
+    /// Number of free tickets every voucher is worth. Voucher tickets are not paid for, so they
+    /// neither cost the creator nor add to the revenue
+    pub tickets_per_voucher: u128,
+
+    /// Free lottery where every account enters with a single ticket, and the prize comes from
+    /// the prize pool only. Requires an entry cost of `0`.
+    pub giveaway: bool,
+
+    /// Allows the creator to enter their own lottery. Off by default, as the creator controls
+    /// the draw timing and the seed.
+    pub allow_creator_entry: bool,
+
+    /// Keeps the winner secret. Instead of the winner being published, the winner claims the
+    /// prize as a token payout by proving they hold the claim secret of the winning ticket, see
+    /// [`crate::claim_to_payout_address`].
+    pub anonymous_winner: bool,
+}
+
+impl LotteryOptions {
+    /// Reward paid to the referrer for every ticket purchased at `ticket_price`
+    pub fn referral_reward_per_ticket(&self, ticket_price: u128) -> u128 {
+        let percentage = self.referral_reward_percentage as u128;
+        // Split to avoid overflowing on large entry costs
+        ticket_price / 100 * percentage + ticket_price % 100 * percentage / 100
+    }
+
+    /// The discount tiers, padded at the front with tiers at `entry_cost` up to
+    /// [`MAX_DISCOUNT_TIERS`], so that they can be passed to the ticket purchase computations.
+    pub fn padded_discount_tiers(&self, entry_cost: u128) -> [DiscountTier; MAX_DISCOUNT_TIERS] {
+        let padding = MAX_DISCOUNT_TIERS - self.discount_tiers.len();
+
+        std::array::from_fn(|i| {
+            if i < padding {
+                DiscountTier {
+                    min_tickets: 0,
+                    ticket_price: entry_cost,
+                }
+            } else {
+                self.discount_tiers[i - padding].clone()
+            }
+        })
+    }
+
+    /// Price per ticket when purchasing `tickets` tickets
+    pub fn ticket_price(&self, tickets: u128, entry_cost: u128) -> u128 {
+        self.discount_tiers
+            .iter()
+            .rev()
+            .find(|tier| tickets >= tier.min_tickets)
+            .map_or(entry_cost, |tier| tier.ticket_price)
+    }
+}
+
+/// Public statistics of a lottery, opened after the draw if the creator opted in.
+#[derive(ReadWriteState, CreateTypeSpec, Clone, Debug)]
+pub struct LotteryStatistics {
+    /// Total number of tickets sold
+    pub tickets: u128,
+
+    /// Number of distinct purchaser accounts
+    pub purchasers: u128,
+
+    /// Gross revenue of paid ticket sales, before referral rewards. Voucher tickets are not paid
+    /// for, so they bring no revenue
+    pub revenue: u128,
+}
+
+/// Records when a lottery moved to a new status, and which computation caused it.
+#[derive(ReadWriteState, Debug, CreateTypeSpec, Clone)]
+pub struct LotteryTransition {
+    /// Block production time at which the transition happened
+    pub timestamp: i64,
+
+    /// Secret variable produced or opened by the computation that caused the transition
+    pub computation_id: SecretVarId,
+}
+
+impl LotteryTransition {
+    /// Create a transition happening at the current block production time
+    pub fn at(context: &ContractContext, computation_id: SecretVarId) -> Self {
+        LotteryTransition {
+            timestamp: context.block_production_time,
+            computation_id,
+        }
+    }
+}
+
 /// Represents the complete state of a lottery instance.
 ///
 /// This structure maintains all data related to a single lottery, including
@@ -54,7 +207,7 @@ pub struct LotteryState {
     /// Unix timestamp representing when the lottery entry period closes
     pub deadline: i64,
 
-    /// Address of the winning participant (if drawn)
+    /// Address of the winning participant (if drawn), never set for an anonymous winner
     pub winner: Option<Address>,
 
     /// Cost to enter the lottery
@@ -73,4 +226,80 @@ pub struct LotteryState {
 
     /// Index of the winning ticket (once drawn)
     pub winner_index: Option<u128>,
+
+    /// Block production time at which the lottery was created
+    pub created_at: i64,
+
+    /// When the lottery was opened, by the lottery creation result
+    pub opened: Option<LotteryTransition>,
+
+    /// When the lottery was closed, by opening the secret lottery state
+    pub closed: Option<LotteryTransition>,
+
+    /// When the winner was drawn, by the draw result
+    pub drawn: Option<LotteryTransition>,
+
+    /// When the prize was claimed, by the updated lottery account
+    pub claimed: Option<LotteryTransition>,
+
+    /// Options chosen by the creator
+    pub options: LotteryOptions,
+
+    /// Statistics of the lottery, once drawn (if published)
+    pub statistics: Option<LotteryStatistics>,
+
+    /// Latest secret-shared [`SalesReport`](crate::zk_compute::SalesReport) revealed to the creator
+    pub sales_report_id: Option<SecretVarId>,
+
+    /// Secret-shared [`LotteryCreationSecret`](crate::zk_compute::LotteryCreationSecret), kept
+    /// while the lottery accepts entries to verify the access code of ticket purchases
+    pub creation_secret_id: Option<SecretVarId>,
+
+    /// Number of vouchers registered by the creator, up to [`LotteryOptions::voucher_count`]
+    pub vouchers_registered: u32,
+
+    /// Secret-shared registered and spent [`VoucherSecret`](crate::zk_compute::VoucherSecret)s,
+    /// kept while the lottery accepts entries
+    pub voucher_ids: Vec<SecretVarId>,
+
+    /// Secret-shared [`PrizeClaimSecret`](crate::zk_compute::PrizeClaimSecret) of the anonymous
+    /// winner, kept until the prize is claimed
+    pub winner_claim_id: Option<SecretVarId>,
+
+    /// Addresses that purchased tickets, whose ticket purchases hold their account key, see
+    /// [`ContractState::account_key_rotation_blocker`](crate::ContractState::account_key_rotation_blocker)
+    pub purchasers: Vec<Address>,
+}
+
+impl LotteryState {
+    /// Whether `address` may purchase tickets in this lottery
+    pub fn is_allowed(&self, address: &Address) -> bool {
+        match &self.options.allowlist {
+            Some(allowlist) => allowlist.members.contains(address),
+            None => true,
+        }
+    }
+
+    /// Largest number of tickets that can be purchased at once without the cost exceeding
+    /// [`MAX_AMOUNT`](crate::zk_compute::MAX_AMOUNT) at any price of the lottery.
+    pub fn max_tickets_per_purchase(&self) -> u128 {
+        let max_price = self
+            .options
+            .discount_tiers
+            .iter()
+            .map(|tier| tier.ticket_price)
+            .fold(self.entry_cost, u128::max);
+
+        crate::zk_compute::MAX_AMOUNT
+            .checked_div(max_price)
+            .unwrap_or(crate::zk_compute::MAX_AMOUNT)
+    }
+
+    /// Total cost of purchasing `tickets` tickets, taking discounts into account.
+    /// Produces [`None`] if the cost overflows.
+    pub fn ticket_cost(&self, tickets: u128) -> Option<u128> {
+        self.options
+            .ticket_price(tickets, self.entry_cost)
+            .checked_mul(tickets)
+    }
 }
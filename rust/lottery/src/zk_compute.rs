@@ -17,6 +17,15 @@ const VARIABLE_KIND_DISCRIMINANT_LOTTERY_ACCOUNT: u8 = 4;
 /// Discriminant value for lottery ticket purchase variables
 const VARIABLE_KIND_DISCRIMINANT_LOTTERY_TICKET_PURCHASE: u8 = 11;
 
+/// Discriminant value for registered voucher variables
+const VARIABLE_KIND_DISCRIMINANT_VOUCHER: u8 = 18;
+
+/// Discriminant value for spent voucher variables
+const VARIABLE_KIND_DISCRIMINANT_SPENT_VOUCHER: u8 = 19;
+
+/// Discriminant value for referrer account variables
+const VARIABLE_KIND_DISCRIMINANT_REFERRER_ACCOUNT: u8 = 31;
+
 /// Unique identifier for an account in multi-party computation
 /// Can represent a user account OR a lottery account
 type AccountKey = Sbu128;
@@ -24,6 +33,16 @@ type AccountKey = Sbu128;
 /// Amount of tokens in the MPC computation
 type TokenAmount = Sbu128;
 
+/// Account nonces are below this limit, see [`derive_account_key`]
+const ACCOUNT_NONCE_LIMIT: u128 = 1 << 32;
+
+/// Bit set in every user account key. Lottery IDs are below it, so user account keys never
+/// collide with lottery account keys.
+pub const USER_ACCOUNT_KEY_TAG: u128 = 1 << 127;
+
+/// Largest value a secret amount can hold without being negative, see [`is_negative`]
+pub const MAX_AMOUNT: u128 = 0x7FFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF;
+
 /// Represents an account balance for either a user or a lottery.
 ///
 /// This struct is used with MPC (Multi-party computation) to maintain
@@ -54,7 +73,29 @@ pub struct SecretLotteryState {
 /// Secret-shared information for creating new users (used for input on account creation).
 #[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
 pub struct AccountCreationSecret {
-    /// Secret-shared key used to transfer to the user that is being created.
+    /// Secret randomness that the account key of the new user is derived from.
+    randomness: Sbu128,
+}
+
+/// Secret-shared information for rotating the account key of a user.
+#[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
+pub struct AccountKeyRotationSecret {
+    /// The current account key of the user, proving that the user knows it.
+    old_account_key: AccountKey,
+    /// Secret randomness that the new account key is derived from.
+    randomness: Sbu128,
+}
+
+/// Secret-shared information for migrating an account to a new address.
+#[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
+pub struct AccountMigrationSecret {
+    /// The account key of the account to migrate, proving that the new address holds it.
+    account_key: AccountKey,
+}
+
+/// Account key of a user, only readable by the user.
+#[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
+pub struct AccountKeySecret {
     account_key: AccountKey,
 }
 
@@ -64,6 +105,8 @@ pub struct LotteryCreationSecret {
     lottery_account_key: AccountKey,
     creator_account_key: AccountKey,
     random_seed: Sbu128,
+    /// Code purchasers must provide to enter the lottery, or `0` for a lottery open to everyone.
+    access_code: Sbu128,
 }
 
 /// Secret-shared information for purchasing lottery tickets
@@ -77,6 +120,35 @@ pub struct LotteryTicketPurchaseSecret {
     tickets: TokenAmount,
     /// Entropy provided by purchaser to generate randomness in the lottery.
     entropy: Sbu128,
+    /// The account key of the user that referred the purchaser, or `0` if there is none. Only
+    /// read by lotteries paying referral rewards, see [`purchase_lottery_ticket_with_referral`].
+    referrer_account_key: AccountKey,
+    /// Access code of the lottery as provided by the purchaser, or `0` if the lottery has none.
+    access_code: Sbu128,
+    /// Voucher code to redeem for free tickets, or `0` when paying for the tickets. Paid purchases
+    /// with a voucher code fail, so that [`compute_lottery_statistics`] can tell them apart.
+    voucher_code: Sbu128,
+    /// Secret chosen by the purchaser to claim the prize of a lottery with an anonymous winner,
+    /// see [`PrizeClaimSecret`]. Unlike the account key, no one else can read it. Must not be `0`
+    /// for the prize to be claimable.
+    claim_secret: Sbu128,
+}
+
+/// Secret-shared voucher code, redeemable once for free tickets in a lottery
+#[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
+pub struct VoucherSecret {
+    /// The account key of the lottery that the voucher is for.
+    lottery_account_key: AccountKey,
+    /// The code to provide when redeeming the voucher. Must not be `0`.
+    code: Sbu128,
+}
+
+/// Secret-shared claim of the prize of a lottery with an anonymous winner
+#[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
+pub struct PrizeClaimSecret {
+    /// The claim secret of the winning ticket purchase, see
+    /// [`LotteryTicketPurchaseSecret::claim_secret`].
+    claim_secret: Sbu128,
 }
 
 /// Secret-shared information for drawing a lottery winner
@@ -90,6 +162,8 @@ struct RecipientBalance {
     exists: Sbu1,
     /// The value of the balance.
     recipient_balance: AccountBalance,
+    /// Variable ID of the balance, or `0` if it does not exist.
+    recipient_balance_id: Sbu32,
 }
 
 #[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
@@ -110,9 +184,9 @@ pub struct ComputationResultPub {
 #[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
 pub struct DrawResult {
     lottery_id: AccountKey,
-    /// The winner's account key, if the draw was successful.
+    /// Variable ID of the winning ticket purchase, owned by the winner.
     /// If the draw was not successful, this will be `0`.
-    winner_id: AccountKey,
+    winner_ticket_id: Sbu32,
     /// Whether the computation was successful.
     successful: Sbu1,
 }
@@ -120,13 +194,67 @@ pub struct DrawResult {
 #[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
 pub struct DrawResultPub {
     pub lottery_id: u128,
-    /// The winner's account key, if the draw was successful.
+    /// Variable ID of the winning ticket purchase, owned by the winner.
     /// If the draw was not successful, this will be `0`.
-    pub winner_id: u128,
+    pub winner_ticket_id: u32,
     /// Whether the computation was successful.
     pub successful: bool,
 }
 
+/// Result of a ticket purchase paying a referral reward, see
+/// [`purchase_lottery_ticket_with_referral`].
+#[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
+pub struct ReferralPurchaseResult {
+    /// Number of tokens that the computation effected or tried to effect.
+    amount: TokenAmount,
+    /// Whether the computation was successful.
+    successful: Sbu1,
+    /// Variable ID of the referrer balance that was credited.
+    /// If no referrer was credited, this will be `0`.
+    referrer_balance_id: Sbu32,
+}
+
+#[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
+pub struct ReferralPurchaseResultPub {
+    /// Number of tokens that the computation effected or tried to effect.
+    pub amount: u128,
+    /// Whether the computation was successful.
+    pub successful: bool,
+    /// Variable ID of the referrer balance that was credited.
+    /// If no referrer was credited, this will be `0`.
+    pub referrer_balance_id: u32,
+}
+
+/// Snapshot of the sales of an open lottery, only readable by the creator.
+#[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
+pub struct SalesReport {
+    /// Number of tickets sold so far
+    tickets: Sbu128,
+    /// Current balance of the lottery account, including the prize pool
+    balance: TokenAmount,
+}
+
+/// Statistics of a drawn lottery, opened if the creator chose to publish them.
+#[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
+pub struct LotteryStatistics {
+    /// Total number of tickets sold
+    tickets: Sbu128,
+    /// Gross revenue of paid ticket sales, before referral rewards
+    revenue: TokenAmount,
+    /// Whether the statistics were computed without overflowing
+    successful: Sbu1,
+}
+
+#[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
+pub struct LotteryStatisticsPub {
+    /// Total number of tickets sold
+    pub tickets: u128,
+    /// Gross revenue of paid ticket sales, before referral rewards
+    pub revenue: u128,
+    /// Whether the statistics were computed without overflowing
+    pub successful: bool,
+}
+
 #[derive(Debug, Clone, Copy, CreateTypeSpec, SecretBinary)]
 pub struct SecretLotteryStatePub {
     /// Entropy used to generate randomness in the lottery.
@@ -135,6 +263,44 @@ pub struct SecretLotteryStatePub {
     pub tickets: u128,
 }
 
+/// Outcome of checked secret arithmetic, see [`checked_add`], [`checked_sub`] and [`checked_mul`].
+///
+/// Workaround for the lack of [`Option`] in ZkRust.
+#[derive(Debug, Clone, SecretBinary)]
+struct CheckedAmount {
+    /// Whether the operation stayed within `0..=MAX_AMOUNT`. If not, `value` must not be used.
+    valid: Sbu1,
+    /// The result of the operation.
+    value: Sbu128,
+}
+
+/// Adds two non-negative secret amounts. Invalid if the sum would be negative.
+fn checked_add(x: Sbu128, y: Sbu128) -> CheckedAmount {
+    let value = x + y;
+    CheckedAmount {
+        valid: !is_negative(x) && !is_negative(y) && !is_negative(value),
+        value,
+    }
+}
+
+/// Subtracts `y` from `x`, both non-negative. Invalid if the difference would be negative.
+fn checked_sub(x: Sbu128, y: Sbu128) -> CheckedAmount {
+    let value = x - y;
+    CheckedAmount {
+        valid: !is_negative(x) && !is_negative(y) && !is_negative(value),
+        value,
+    }
+}
+
+/// Multiplies `x` by `factor`. Invalid if `x` exceeds `max_x`, which the caller must choose so
+/// that `max_x` times the largest possible `factor` is at most [`MAX_AMOUNT`].
+fn checked_mul(x: Sbu128, factor: Sbu128, max_x: u128) -> CheckedAmount {
+    CheckedAmount {
+        valid: !is_negative(x) && Sbu128::from(max_x) >= x,
+        value: x * factor,
+    }
+}
+
 /// Finds the balance of the recipient based on the [`AccountKey`].
 ///
 /// Produces a [`RecipientBalance`], with `exists` true if and only if the balance could be found.
@@ -150,6 +316,7 @@ fn find_recipient_balance(
             account_key,
             balance: Sbu128::from(0),
         },
+        recipient_balance_id: Sbu32::from(0),
     };
 
     for variable_id in secret_variable_ids() {
@@ -160,6 +327,7 @@ fn find_recipient_balance(
                 if balance.account_key == account_key {
                     recipient_balance.exists = Sbu1::from(true);
                     recipient_balance.recipient_balance = balance;
+                    recipient_balance.recipient_balance_id = Sbu32::from(variable_id.raw_id);
                 }
             }
         }
@@ -172,26 +340,38 @@ fn find_recipient_balance(
     recipient_balance
 }
 
-/// Initializes a new contract account.
+/// Derives the account key of a new account from the secret `randomness` of its owner.
+///
+/// `account_nonce` is unique per account creation and below [`ACCOUNT_NONCE_LIMIT`]. It fills the
+/// low bits of the key, so keys never collide, while the randomness keeps the key unguessable.
+/// The key is tagged with [`USER_ACCOUNT_KEY_TAG`], keeping it apart from the lottery IDs.
+fn derive_account_key(randomness: Sbu128, account_nonce: u128) -> AccountKey {
+    let account_key = randomness * Sbu128::from(ACCOUNT_NONCE_LIMIT) + Sbu128::from(account_nonce);
+
+    if is_negative(account_key) {
+        account_key
+    } else {
+        account_key + Sbu128::from(USER_ACCOUNT_KEY_TAG)
+    }
+}
+
+/// Initializes a new contract account with a fresh account key, see [`derive_account_key`].
 ///
-/// The computation verifies that the given [`AccountBalance::account_key`] haven't been
-/// used yet. If the `account_key` has been used, it will create a new account with `account_key` zero.
+/// Account creation cannot fail, so it reveals nothing about the keys of other accounts.
+/// Returns:
+/// 0: AccountBalance -> new account balance
+/// 1: ComputationResult -> always successful
+/// 2: AccountKeySecret -> the account key, for the owner of the account
 #[zk_compute(shortname = 0x70)]
 pub fn create_account(
     account_creation_id: SecretVarId,
-    account_key: u128,
-) -> (AccountBalance, ComputationResult) {
-    let mut account_details: AccountCreationSecret =
+    account_nonce: u128,
+) -> (AccountBalance, ComputationResult, AccountKeySecret) {
+    let account_details: AccountCreationSecret =
         load_sbi::<AccountCreationSecret>(account_creation_id);
 
-    let recipient_balance =
-        find_recipient_balance(account_details.account_key, account_creation_id);
+    let account_key = derive_account_key(account_details.randomness, account_nonce);
 
-    let account_key = if recipient_balance.exists {
-        Sbu128::from(0)
-    } else {
-        account_details.account_key
-    };
     (
         AccountBalance {
             account_key,
@@ -199,20 +379,141 @@ pub fn create_account(
         },
         ComputationResult {
             amount: Sbu128::from(0),
-            successful: account_key == account_details.account_key,
+            successful: Sbu1::from(true),
+        },
+        AccountKeySecret { account_key },
+    )
+}
+
+/// Replaces the account key of an existing account, keeping its balance. Only succeeds if the
+/// old account key of the [`AccountKeyRotationSecret`] matches the account.
+///
+/// The new key is derived like in [`create_account`], with `account_nonce` unique per rotation.
+/// Returns:
+/// 0: AccountBalance -> account balance, under the new account key if successful
+/// 1: ComputationResult -> whether the rotation was successful or not
+/// 2: AccountKeySecret -> the current account key, for the owner of the account
+#[zk_compute(shortname = 0x80)]
+pub fn rotate_account_key(
+    rotation_id: SecretVarId,
+    balance_id: SecretVarId,
+    account_nonce: u128,
+) -> (AccountBalance, ComputationResult, AccountKeySecret) {
+    let rotation: AccountKeyRotationSecret = load_sbi::<AccountKeyRotationSecret>(rotation_id);
+    let mut balance: AccountBalance = load_sbi::<AccountBalance>(balance_id);
+
+    let successful = rotation.old_account_key == balance.account_key;
+
+    if successful {
+        balance.account_key = derive_account_key(rotation.randomness, account_nonce);
+    }
+
+    (
+        balance,
+        ComputationResult {
+            amount: Sbu128::from(0),
+            successful,
+        },
+        AccountKeySecret {
+            account_key: balance.account_key,
+        },
+    )
+}
+
+/// Verifies that the [`AccountMigrationSecret`] holds the account key of the account to migrate.
+/// The account itself is moved by the contract, as the balance does not change.
+/// Returns:
+/// 0: ComputationResult -> whether the account key matches or not
+#[zk_compute(shortname = 0x81)]
+pub fn verify_account_migration(
+    migration_id: SecretVarId,
+    balance_id: SecretVarId,
+) -> ComputationResult {
+    let migration: AccountMigrationSecret = load_sbi::<AccountMigrationSecret>(migration_id);
+    let balance: AccountBalance = load_sbi::<AccountBalance>(balance_id);
+
+    ComputationResult {
+        amount: Sbu128::from(0),
+        successful: migration.account_key == balance.account_key,
+    }
+}
+
+/// Burns the entire balance of an account that is being closed, see [`burn_credits`].
+/// Returns:
+/// 0: ComputationResult -> the burned balance, to be withdrawn
+#[zk_compute(shortname = 0x82)]
+pub fn burn_all_credits(balance_id: SecretVarId) -> ComputationResult {
+    let balance: AccountBalance = load_sbi::<AccountBalance>(balance_id);
+
+    ComputationResult {
+        amount: balance.balance,
+        successful: Sbu1::from(true),
+    }
+}
+
+/// Initializes a new contract account with an initial balance of `amount`.
+///
+/// Behaves like [`create_account`], but credits the deposited tokens to the new account. If the
+/// deposit exceeds [`MAX_AMOUNT`], the account is created without balance, and the returned
+/// [`ComputationResult::amount`] is the amount that must be refunded.
+#[zk_compute(shortname = 0x77)]
+pub fn create_account_with_deposit(
+    account_creation_id: SecretVarId,
+    account_nonce: u128,
+    amount: u128,
+) -> (AccountBalance, ComputationResult, AccountKeySecret) {
+    let account_details: AccountCreationSecret =
+        load_sbi::<AccountCreationSecret>(account_creation_id);
+
+    let account_key = derive_account_key(account_details.randomness, account_nonce);
+    let mut balance = Sbu128::from(amount);
+    let successful = !is_negative(balance);
+
+    if !successful {
+        balance = Sbu128::from(0);
+    }
+
+    (
+        AccountBalance {
+            account_key,
+            balance,
+        },
+        ComputationResult {
+            amount: Sbu128::from(amount),
+            successful,
         },
+        AccountKeySecret { account_key },
     )
 }
 
+/// Credits `amount` to the balance. If the balance would overflow it is left unchanged, and the
+/// returned [`ComputationResult::amount`] is the amount that must be refunded.
 #[zk_compute(shortname = 0x71)]
-pub fn mint_credits(sender_balance_id: SecretVarId, amount: u128) -> AccountBalance {
+pub fn mint_credits(
+    sender_balance_id: SecretVarId,
+    amount: u128,
+) -> (AccountBalance, ComputationResult) {
     let mut sender_balance: AccountBalance = load_sbi::<AccountBalance>(sender_balance_id);
+    let mut successful = Sbu1::from(false);
+
+    let new_balance = checked_add(sender_balance.balance, Sbu128::from(amount));
 
-    // Update the sender balance.
-    sender_balance.balance = sender_balance.balance + Sbu128::from(amount);
+    if new_balance.valid {
+        // Update the sender balance.
+        sender_balance.balance = new_balance.value;
+
+        // Mark the operation as successful.
+        successful = Sbu1::from(true);
+    }
 
     // Return the updated sender balance.
-    (sender_balance)
+    (
+        sender_balance,
+        ComputationResult {
+            amount: Sbu128::from(amount),
+            successful,
+        },
+    )
 }
 
 #[zk_compute(shortname = 0x72)]
@@ -220,10 +521,12 @@ pub fn burn_credits(balance_id: SecretVarId, amount: u128) -> (AccountBalance, C
     let mut balance: AccountBalance = load_sbi::<AccountBalance>(balance_id);
     let mut successful = Sbu1::from(false);
 
+    let new_balance = checked_sub(balance.balance, Sbu128::from(amount));
+
     // If insufficient balance, do not burn credits.
-    if !is_negative(balance.balance - Sbu128::from(amount)) {
+    if new_balance.valid {
         // Update the sender balance.
-        balance.balance = balance.balance - Sbu128::from(amount);
+        balance.balance = new_balance.value;
 
         // Mark the operation as successful.
         successful = Sbu1::from(true);
@@ -244,10 +547,14 @@ pub fn burn_credits(balance_id: SecretVarId, amount: u128) -> (AccountBalance, C
 // 1: AccountBalance -> new lottery balance
 // 2: SecretLotteryState -> new lottery state
 // 3: Whether the creation was successful or not
+//
+// The secret lottery account key must be the public `lottery_id`, which the contract keeps below
+// [`USER_ACCOUNT_KEY_TAG`]. A failed creation thus never tells whether a user account key exists.
 #[zk_compute(shortname = 0x73)]
 pub fn create_lottery(
     lottery_creation_id: SecretVarId,
     creator_balance_id: SecretVarId,
+    lottery_id: u128,
     prize_pool: u128,
 ) -> (
     AccountBalance,
@@ -260,6 +567,7 @@ pub fn create_lottery(
         load_sbi::<LotteryCreationSecret>(lottery_creation_id);
 
     let secret_amount = Sbu128::from(prize_pool);
+    let new_creator_balance = checked_sub(creator_balance.balance, secret_amount);
     let mut successful = Sbu1::from(false);
 
     let recipient_balance = find_recipient_balance(
@@ -274,14 +582,17 @@ pub fn create_lottery(
         lottery_creation_secret.lottery_account_key
     };
 
+    // The creator key must belong to the creator, so that creators can be kept out of their own lottery
     if !is_negative(account_key - Sbu128::from(1))
-        && !is_negative(creator_balance.balance - secret_amount)
+        && lottery_creation_secret.lottery_account_key == Sbu128::from(lottery_id)
+        && lottery_creation_secret.creator_account_key == creator_balance.account_key
+        && new_creator_balance.valid
     {
         // If the account key didn't conflict, we'll have a positive number here for key and will be able to mark this as successful
         successful = Sbu1::from(true);
 
         // Decrease the credits from the sender balance
-        creator_balance.balance = creator_balance.balance - secret_amount;
+        creator_balance.balance = new_creator_balance.value;
     }
 
     let mut lottery_balance = AccountBalance {
@@ -303,18 +614,88 @@ pub fn create_lottery(
     )
 }
 
+/// Produces true if the ticket purchase carries the access code of the lottery.
+fn has_access_code(
+    lottery_ticket_purchase_secret: LotteryTicketPurchaseSecret,
+    lottery_creation_id: SecretVarId,
+) -> Sbu1 {
+    let lottery_creation_secret: LotteryCreationSecret =
+        load_sbi::<LotteryCreationSecret>(lottery_creation_id);
+
+    lottery_ticket_purchase_secret.access_code == lottery_creation_secret.access_code
+}
+
+/// Whether the owner of `purchaser_account_key` may enter the lottery.
+///
+/// Creators control the draw, so they can only enter their own lottery if it allows them to.
+fn may_enter(
+    purchaser_account_key: AccountKey,
+    lottery_creation_id: SecretVarId,
+    allow_creator_entry: bool,
+) -> Sbu1 {
+    let lottery_creation_secret: LotteryCreationSecret =
+        load_sbi::<LotteryCreationSecret>(lottery_creation_id);
+
+    let mut allowed = Sbu1::from(true);
+    if !allow_creator_entry {
+        allowed = purchaser_account_key != lottery_creation_secret.creator_account_key;
+    }
+
+    allowed
+}
+
+/// Price per ticket when purchasing `tickets` tickets, without revealing the number of tickets.
+///
+/// Tiers must be in ascending order of minimum tickets, so the last tier that applies wins.
+/// Unused tiers are padded with a minimum of `0` and the base price.
+#[allow(clippy::too_many_arguments)]
+fn discounted_ticket_price(
+    tickets: Sbu128,
+    base_price: u128,
+    tier_1_min_tickets: u128,
+    tier_1_price: u128,
+    tier_2_min_tickets: u128,
+    tier_2_price: u128,
+    tier_3_min_tickets: u128,
+    tier_3_price: u128,
+) -> Sbu128 {
+    let mut price = Sbu128::from(base_price);
+
+    if tickets >= Sbu128::from(tier_1_min_tickets) {
+        price = Sbu128::from(tier_1_price);
+    }
+    if tickets >= Sbu128::from(tier_2_min_tickets) {
+        price = Sbu128::from(tier_2_price);
+    }
+    if tickets >= Sbu128::from(tier_3_min_tickets) {
+        price = Sbu128::from(tier_3_price);
+    }
+
+    price
+}
+
 // Returns:
 // 0: AccountBalance -> updated purchaser account balance
 // 1: AccountBalance -> updated lottery account balance
 // 2: SecretLotteryState -> new lottery state
 // 3: ComputationResult -> whether the purchase was successful or not
+#[allow(clippy::too_many_arguments)]
 #[zk_compute(shortname = 0x74)]
 pub fn purchase_lottery_ticket(
     lottery_ticket_purchase_id: SecretVarId,
     purchaser_balance_id: SecretVarId,
     lottery_balance_id: SecretVarId,
     lottery_state_id: SecretVarId,
+    lottery_creation_id: SecretVarId,
+    allow_creator_entry: bool,
     ticket_price: u128,
+    max_tickets: u128,
+    tier_1_min_tickets: u128,
+    tier_1_price: u128,
+    tier_2_min_tickets: u128,
+    tier_2_price: u128,
+    tier_3_min_tickets: u128,
+    tier_3_price: u128,
 ) -> (
     AccountBalance,
     AccountBalance,
@@ -329,19 +710,50 @@ pub fn purchase_lottery_ticket(
 
     let mut lottery_state: SecretLotteryState = load_sbi::<SecretLotteryState>(lottery_state_id);
 
-    let secret_amount = lottery_ticket_purchase_secret.tickets * Sbu128::from(ticket_price);
+    let price = discounted_ticket_price(
+        lottery_ticket_purchase_secret.tickets,
+        ticket_price,
+        tier_1_min_tickets,
+        tier_1_price,
+        tier_2_min_tickets,
+        tier_2_price,
+        tier_3_min_tickets,
+        tier_3_price,
+    );
+    let secret_amount = checked_mul(lottery_ticket_purchase_secret.tickets, price, max_tickets);
+    let new_purchaser_balance = checked_sub(purchaser_balance.balance, secret_amount.value);
+    let new_lottery_balance = checked_add(lottery_balance.balance, secret_amount.value);
+    let new_tickets = checked_add(
+        lottery_state.tickets,
+        lottery_ticket_purchase_secret.tickets,
+    );
     let mut successful = Sbu1::from(false);
 
-    if !is_negative(purchaser_balance.balance - secret_amount) {
+    // The purchaser must know the access code, may enter and must have enough balance
+    if has_access_code(lottery_ticket_purchase_secret, lottery_creation_id)
+        && lottery_ticket_purchase_secret.lottery_account_key == lottery_balance.account_key
+        && lottery_ticket_purchase_secret.purchaser_account_key == purchaser_balance.account_key
+        && lottery_ticket_purchase_secret.voucher_code == Sbu128::from(0)
+        && may_enter(
+            purchaser_balance.account_key,
+            lottery_creation_id,
+            allow_creator_entry,
+        )
+        && lottery_ticket_purchase_secret.tickets >= Sbu128::from(1)
+        && secret_amount.valid
+        && new_purchaser_balance.valid
+        && new_lottery_balance.valid
+        && new_tickets.valid
+    {
         // If the purchaser has enough balance, we can proceed with the purchase
         successful = Sbu1::from(true);
 
         // Decrease the credits from the sender balance
-        purchaser_balance.balance = purchaser_balance.balance - secret_amount;
+        purchaser_balance.balance = new_purchaser_balance.value;
         // Increase the lottery balance
-        lottery_balance.balance = lottery_balance.balance + secret_amount;
+        lottery_balance.balance = new_lottery_balance.value;
 
-        lottery_state.tickets = lottery_state.tickets + lottery_ticket_purchase_secret.tickets;
+        lottery_state.tickets = new_tickets.value;
         lottery_state.entropy = lottery_state.entropy + lottery_ticket_purchase_secret.entropy;
     }
 
@@ -356,12 +768,232 @@ pub fn purchase_lottery_ticket(
     )
 }
 
+// Same as `purchase_lottery_ticket`, but credits the referral reward for every ticket to the
+// referrer balance, found by the secret referrer key. The reward per ticket is selected with the
+// same discount tiers as the price. The reward is only paid if the key belongs to another user
+// account, otherwise the full spend goes to the lottery. The credited balance is revealed by the
+// result, so the contract can replace it with the updated referrer balance.
+// Returns:
+// 0: AccountBalance -> updated purchaser account balance
+// 1: AccountBalance -> updated lottery account balance
+// 2: SecretLotteryState -> new lottery state
+// 3: ReferralPurchaseResult -> whether the purchase was successful or not, and the referrer
+//    balance that was credited, if any
+// 4: AccountBalance -> updated referrer account balance, or an empty balance if there is none
+#[allow(clippy::too_many_arguments)]
+#[zk_compute(shortname = 0x7B)]
+pub fn purchase_lottery_ticket_with_referral(
+    lottery_ticket_purchase_id: SecretVarId,
+    purchaser_balance_id: SecretVarId,
+    lottery_balance_id: SecretVarId,
+    lottery_state_id: SecretVarId,
+    lottery_creation_id: SecretVarId,
+    allow_creator_entry: bool,
+    ticket_price: u128,
+    max_tickets: u128,
+    referral_reward_per_ticket: u128,
+    tier_1_min_tickets: u128,
+    tier_1_price: u128,
+    tier_1_reward: u128,
+    tier_2_min_tickets: u128,
+    tier_2_price: u128,
+    tier_2_reward: u128,
+    tier_3_min_tickets: u128,
+    tier_3_price: u128,
+    tier_3_reward: u128,
+) -> (
+    AccountBalance,
+    AccountBalance,
+    SecretLotteryState,
+    ReferralPurchaseResult,
+    AccountBalance,
+) {
+    let lottery_ticket_purchase_secret: LotteryTicketPurchaseSecret =
+        load_sbi::<LotteryTicketPurchaseSecret>(lottery_ticket_purchase_id);
+
+    let mut purchaser_balance: AccountBalance = load_sbi::<AccountBalance>(purchaser_balance_id);
+    let mut lottery_balance: AccountBalance = load_sbi::<AccountBalance>(lottery_balance_id);
+
+    let mut lottery_state: SecretLotteryState = load_sbi::<SecretLotteryState>(lottery_state_id);
+
+    let price = discounted_ticket_price(
+        lottery_ticket_purchase_secret.tickets,
+        ticket_price,
+        tier_1_min_tickets,
+        tier_1_price,
+        tier_2_min_tickets,
+        tier_2_price,
+        tier_3_min_tickets,
+        tier_3_price,
+    );
+    let reward_per_ticket = discounted_ticket_price(
+        lottery_ticket_purchase_secret.tickets,
+        referral_reward_per_ticket,
+        tier_1_min_tickets,
+        tier_1_reward,
+        tier_2_min_tickets,
+        tier_2_reward,
+        tier_3_min_tickets,
+        tier_3_reward,
+    );
+    // Rewards never exceed the price, so the same bound applies
+    let secret_amount = checked_mul(lottery_ticket_purchase_secret.tickets, price, max_tickets);
+    let reward = checked_mul(
+        lottery_ticket_purchase_secret.tickets,
+        reward_per_ticket,
+        max_tickets,
+    );
+    let new_purchaser_balance = checked_sub(purchaser_balance.balance, secret_amount.value);
+    let new_lottery_balance = checked_add(lottery_balance.balance, secret_amount.value);
+    let new_tickets = checked_add(
+        lottery_state.tickets,
+        lottery_ticket_purchase_secret.tickets,
+    );
+    let mut successful = Sbu1::from(false);
+    let mut referrer_balance_id = Sbu32::from(0);
+    let mut referrer_balance = AccountBalance {
+        account_key: Sbu128::from(0),
+        balance: Sbu128::from(0),
+    };
+
+    // Purchaser is excluded from the search, so purchasers cannot refer themselves
+    let referrer = find_recipient_balance(
+        lottery_ticket_purchase_secret.referrer_account_key,
+        purchaser_balance_id,
+    );
+    let new_referrer_balance = checked_add(referrer.recipient_balance.balance, reward.value);
+
+    // The purchaser must know the access code, may enter and must have enough balance
+    if has_access_code(lottery_ticket_purchase_secret, lottery_creation_id)
+        && lottery_ticket_purchase_secret.lottery_account_key == lottery_balance.account_key
+        && lottery_ticket_purchase_secret.purchaser_account_key == purchaser_balance.account_key
+        && lottery_ticket_purchase_secret.voucher_code == Sbu128::from(0)
+        && may_enter(
+            purchaser_balance.account_key,
+            lottery_creation_id,
+            allow_creator_entry,
+        )
+        && lottery_ticket_purchase_secret.tickets >= Sbu128::from(1)
+        && secret_amount.valid
+        && new_purchaser_balance.valid
+        && new_lottery_balance.valid
+        && new_tickets.valid
+    {
+        // If the purchaser has enough balance, we can proceed with the purchase
+        successful = Sbu1::from(true);
+
+        // Decrease the credits from the sender balance
+        purchaser_balance.balance = new_purchaser_balance.value;
+
+        // Lottery balances are found as well, so only user account keys may refer
+        if referrer.exists
+            && is_user_account_key(referrer.recipient_balance.account_key)
+            && new_referrer_balance.valid
+        {
+            // Split the spend between the referrer and the lottery
+            referrer_balance.account_key = referrer.recipient_balance.account_key;
+            referrer_balance.balance = new_referrer_balance.value;
+            referrer_balance_id = referrer.recipient_balance_id;
+            lottery_balance.balance = new_lottery_balance.value - reward.value;
+        } else {
+            // Unknown referrer, the lottery receives the full spend
+            lottery_balance.balance = new_lottery_balance.value;
+        }
+
+        lottery_state.tickets = new_tickets.value;
+        lottery_state.entropy = lottery_state.entropy + lottery_ticket_purchase_secret.entropy;
+    }
+
+    (
+        purchaser_balance,
+        lottery_balance,
+        lottery_state,
+        ReferralPurchaseResult {
+            amount: Sbu128::from(0),
+            successful,
+            referrer_balance_id,
+        },
+        referrer_balance,
+    )
+}
+
+// Records a ticket purchase that has been paid for directly with `cost` tokens, crediting the
+// lottery account without touching the purchaser balance. The purchaser balance is only read to
+// check that the ticket belongs to the purchaser, so the winner can be resolved.
+// Returns:
+// 0: AccountBalance -> updated lottery account balance
+// 1: SecretLotteryState -> new lottery state
+// 2: ComputationResult -> whether the purchase was successful or not, and the amount to refund
+#[allow(clippy::too_many_arguments)]
+#[zk_compute(shortname = 0x78)]
+pub fn purchase_lottery_ticket_with_tokens(
+    lottery_ticket_purchase_id: SecretVarId,
+    purchaser_balance_id: SecretVarId,
+    lottery_balance_id: SecretVarId,
+    lottery_state_id: SecretVarId,
+    lottery_creation_id: SecretVarId,
+    allow_creator_entry: bool,
+    tickets: u128,
+    cost: u128,
+) -> (AccountBalance, SecretLotteryState, ComputationResult) {
+    let lottery_ticket_purchase_secret: LotteryTicketPurchaseSecret =
+        load_sbi::<LotteryTicketPurchaseSecret>(lottery_ticket_purchase_id);
+
+    let purchaser_balance: AccountBalance = load_sbi::<AccountBalance>(purchaser_balance_id);
+    let mut lottery_balance: AccountBalance = load_sbi::<AccountBalance>(lottery_balance_id);
+
+    let mut lottery_state: SecretLotteryState = load_sbi::<SecretLotteryState>(lottery_state_id);
+
+    let new_lottery_balance = checked_add(lottery_balance.balance, Sbu128::from(cost));
+    let new_tickets = checked_add(
+        lottery_state.tickets,
+        lottery_ticket_purchase_secret.tickets,
+    );
+    let mut successful = Sbu1::from(false);
+
+    // The secret ticket must match what has been paid for
+    if lottery_ticket_purchase_secret.tickets == Sbu128::from(tickets)
+        && lottery_ticket_purchase_secret.purchaser_account_key == purchaser_balance.account_key
+        && lottery_ticket_purchase_secret.lottery_account_key == lottery_balance.account_key
+        && lottery_ticket_purchase_secret.voucher_code == Sbu128::from(0)
+        && has_access_code(lottery_ticket_purchase_secret, lottery_creation_id)
+        && may_enter(
+            purchaser_balance.account_key,
+            lottery_creation_id,
+            allow_creator_entry,
+        )
+        && new_lottery_balance.valid
+        && new_tickets.valid
+    {
+        successful = Sbu1::from(true);
+
+        // Increase the lottery balance by the tokens paid
+        lottery_balance.balance = new_lottery_balance.value;
+
+        lottery_state.tickets = new_tickets.value;
+        lottery_state.entropy = lottery_state.entropy + lottery_ticket_purchase_secret.entropy;
+    }
+
+    (
+        lottery_balance,
+        lottery_state,
+        ComputationResult {
+            amount: Sbu128::from(cost),
+            successful,
+        },
+    )
+}
+
 // Picks a winner from the lottery using the generated entropy with the modulus of the number of tickets to find the winner.
 // (Winner claims their winnings by calling `claim_winnings` function - cannot be done here as we need the winner known in metadata to handle balance change).
+// For an `anonymous` lottery the winning ticket is not published, and the winner instead claims
+// the prize by proving they hold the claim secret of the winning ticket, see
+// [`claim_winnings_to_payout`].
 // Returns:
 // 0: AccountBalance -> Update lottery balance
 // 1: AccountBalance -> Creator balance
 // 2: DrawResult -> whether the winner was successfully drawn or not
+// 3: PrizeClaimSecret -> claim secret of the winning ticket purchase, or `0` if there is none
 #[zk_compute(shortname = 0x75)]
 pub fn draw_lottery_winner(
     secret_lottery_state_id: SecretVarId,
@@ -370,14 +1002,16 @@ pub fn draw_lottery_winner(
     entry_cost: u128,
     prize_pool: u128,
     winner_index: u128,
-) -> (AccountBalance, AccountBalance, DrawResult) {
+    anonymous: bool,
+) -> (AccountBalance, AccountBalance, DrawResult, PrizeClaimSecret) {
     let mut lottery_state: SecretLotteryState =
         load_sbi::<SecretLotteryState>(secret_lottery_state_id);
     let mut lottery_balance: AccountBalance = load_sbi::<AccountBalance>(lottery_balance_id);
     let mut creator_balance: AccountBalance = load_sbi::<AccountBalance>(creator_balance_id);
 
     let total_tickets = lottery_state.tickets;
-    let mut winner_id = Sbu128::from(0);
+    let mut winner_ticket_id = Sbu32::from(0);
+    let mut winner_claim_secret = Sbu128::from(0);
     let lottery_account_key = lottery_balance.account_key;
 
     // Iterate over the entries until we find the winner
@@ -394,17 +1028,21 @@ pub fn draw_lottery_winner(
             if (ticket.lottery_account_key == lottery_account_key) {
                 // Found a lottery ticket
                 if sbu_winner_index >= cidx && sbu_winner_index < cidx + ticket.tickets {
-                    // Found the winner
-                    winner_id = ticket.purchaser_account_key;
+                    // Found the winner, identified by the ticket so the account key stays secret
+                    winner_ticket_id = Sbu32::from(variable_id.raw_id);
+                    winner_claim_secret = ticket.claim_secret;
 
-                    let remainder_balance = lottery_balance.balance - Sbu128::from(prize_pool);
+                    let remainder_balance =
+                        checked_sub(lottery_balance.balance, Sbu128::from(prize_pool));
+                    let new_creator_balance =
+                        checked_add(creator_balance.balance, remainder_balance.value);
 
-                    if !is_negative(remainder_balance) {
+                    if remainder_balance.valid && new_creator_balance.valid {
                         // Move the remainder to the creator's balance
-                        creator_balance.balance = creator_balance.balance + remainder_balance;
+                        creator_balance.balance = new_creator_balance.value;
 
                         // Reduce the lottery balance by the amount of the remainder
-                        lottery_balance.balance = lottery_balance.balance - remainder_balance;
+                        lottery_balance.balance = Sbu128::from(prize_pool);
 
                         // Winner will claim in a separate flow
                     }
@@ -416,28 +1054,293 @@ pub fn draw_lottery_winner(
         }
     }
 
-    // If the winner_id is still 0, it means no tickets were purchased
+    // If the winner_ticket_id is still 0, it means no tickets were purchased
     // We do it after the loop because of public/secret context level errors being thrown
     // if we try to do an if/else to check ticket count
-    if winner_id == Sbu128::from(0) {
+    let refunded_creator_balance = checked_add(creator_balance.balance, lottery_balance.balance);
+    if winner_ticket_id == Sbu32::from(0) && refunded_creator_balance.valid {
         // Transfer the entire balance back to creator
-        creator_balance.balance = creator_balance.balance + lottery_balance.balance;
+        creator_balance.balance = refunded_creator_balance.value;
 
         // If no winner was found, we reset the lottery balance to zero
         lottery_balance.balance = Sbu128::from(0);
     }
 
+    let successful = winner_ticket_id != Sbu32::from(0);
+    if anonymous {
+        // The owner of the winning ticket would reveal the winner
+        winner_ticket_id = Sbu32::from(0);
+    }
+
     (
         lottery_balance,
         creator_balance,
         DrawResult {
             lottery_id: lottery_account_key,
-            winner_id,
-            successful: winner_id != Sbu128::from(0),
+            winner_ticket_id,
+            successful,
+        },
+        PrizeClaimSecret {
+            claim_secret: winner_claim_secret,
+        },
+    )
+}
+
+/// Copies the balance of a user account, for the owner of the account to read.
+/// The balance itself is not changed.
+/// Returns:
+/// 0: AccountBalance -> copy of the account balance
+#[zk_compute(shortname = 0x83)]
+pub fn reveal_balance(balance_id: SecretVarId) -> AccountBalance {
+    load_sbi::<AccountBalance>(balance_id)
+}
+
+/// Copies the current ticket count and lottery balance into a [`SalesReport`] for the creator.
+/// Neither the secret lottery state nor the lottery balance is changed.
+/// Returns:
+/// 0: SalesReport -> snapshot of the lottery sales
+#[zk_compute(shortname = 0x7A)]
+pub fn reveal_sales(lottery_state_id: SecretVarId, lottery_balance_id: SecretVarId) -> SalesReport {
+    let lottery_state: SecretLotteryState = load_sbi::<SecretLotteryState>(lottery_state_id);
+    let lottery_balance: AccountBalance = load_sbi::<AccountBalance>(lottery_balance_id);
+
+    SalesReport {
+        tickets: lottery_state.tickets,
+        balance: lottery_balance.balance,
+    }
+}
+
+/// Redeems a voucher of the lottery for `tickets_per_voucher` free tickets.
+///
+/// The voucher code of the ticket purchase must match a registered [`VoucherSecret`] of the
+/// lottery that has not been spent yet. The tickets are free entries, so no balance changes.
+/// Returns:
+/// 0: SecretLotteryState -> new lottery state
+/// 1: ComputationResult -> whether the redemption was successful or not
+/// 2: VoucherSecret -> the spent voucher, with code `0` if the redemption failed
+#[zk_compute(shortname = 0x7C)]
+pub fn redeem_voucher(
+    lottery_ticket_purchase_id: SecretVarId,
+    purchaser_balance_id: SecretVarId,
+    lottery_state_id: SecretVarId,
+    lottery_creation_id: SecretVarId,
+    allow_creator_entry: bool,
+    tickets_per_voucher: u128,
+) -> (SecretLotteryState, ComputationResult, VoucherSecret) {
+    let lottery_ticket_purchase_secret: LotteryTicketPurchaseSecret =
+        load_sbi::<LotteryTicketPurchaseSecret>(lottery_ticket_purchase_id);
+    let purchaser_balance: AccountBalance = load_sbi::<AccountBalance>(purchaser_balance_id);
+    let lottery_creation_secret: LotteryCreationSecret =
+        load_sbi::<LotteryCreationSecret>(lottery_creation_id);
+
+    let mut lottery_state: SecretLotteryState = load_sbi::<SecretLotteryState>(lottery_state_id);
+
+    let lottery_account_key = lottery_creation_secret.lottery_account_key;
+    let code = lottery_ticket_purchase_secret.voucher_code;
+
+    let mut registered = Sbu1::from(false);
+    let mut spent = Sbu1::from(false);
+
+    for variable_id in secret_variable_ids() {
+        let kind = load_metadata::<u8>(variable_id);
+
+        if kind == VARIABLE_KIND_DISCRIMINANT_VOUCHER {
+            let voucher: VoucherSecret = load_sbi::<VoucherSecret>(variable_id);
+            if voucher.lottery_account_key == lottery_account_key && voucher.code == code {
+                registered = Sbu1::from(true);
+            }
+        } else if kind == VARIABLE_KIND_DISCRIMINANT_SPENT_VOUCHER {
+            let voucher: VoucherSecret = load_sbi::<VoucherSecret>(variable_id);
+            if voucher.lottery_account_key == lottery_account_key && voucher.code == code {
+                spent = Sbu1::from(true);
+            }
+        }
+    }
+
+    let new_tickets = checked_add(
+        lottery_state.tickets,
+        lottery_ticket_purchase_secret.tickets,
+    );
+    let mut successful = Sbu1::from(false);
+    let mut spent_voucher = VoucherSecret {
+        lottery_account_key,
+        code: Sbu128::from(0),
+    };
+
+    if registered
+        && !spent
+        && code != Sbu128::from(0)
+        && lottery_ticket_purchase_secret.tickets == Sbu128::from(tickets_per_voucher)
+        && new_tickets.valid
+        && lottery_ticket_purchase_secret.lottery_account_key == lottery_account_key
+        && lottery_ticket_purchase_secret.purchaser_account_key == purchaser_balance.account_key
+        && has_access_code(lottery_ticket_purchase_secret, lottery_creation_id)
+        && may_enter(
+            purchaser_balance.account_key,
+            lottery_creation_id,
+            allow_creator_entry,
+        )
+    {
+        successful = Sbu1::from(true);
+
+        // Mark the voucher as spent
+        spent_voucher.code = code;
+
+        lottery_state.tickets = new_tickets.value;
+        lottery_state.entropy = lottery_state.entropy + lottery_ticket_purchase_secret.entropy;
+    }
+
+    (
+        lottery_state,
+        ComputationResult {
+            amount: Sbu128::from(0),
+            successful,
+        },
+        spent_voucher,
+    )
+}
+
+/// Enters a giveaway lottery with a single free ticket.
+///
+/// Every account can enter once, so the entry fails if an earlier ticket purchase with the same
+/// purchaser and lottery keys exists. The purchaser key must belong to the purchaser balance, so
+/// that accounts cannot enter again under another key.
+/// Returns:
+/// 0: SecretLotteryState -> new lottery state
+/// 1: ComputationResult -> whether the entry was successful or not
+#[zk_compute(shortname = 0x7D)]
+pub fn enter_giveaway(
+    lottery_ticket_purchase_id: SecretVarId,
+    purchaser_balance_id: SecretVarId,
+    lottery_state_id: SecretVarId,
+    lottery_creation_id: SecretVarId,
+    allow_creator_entry: bool,
+) -> (SecretLotteryState, ComputationResult) {
+    let lottery_ticket_purchase_secret: LotteryTicketPurchaseSecret =
+        load_sbi::<LotteryTicketPurchaseSecret>(lottery_ticket_purchase_id);
+    let purchaser_balance: AccountBalance = load_sbi::<AccountBalance>(purchaser_balance_id);
+    let lottery_creation_secret: LotteryCreationSecret =
+        load_sbi::<LotteryCreationSecret>(lottery_creation_id);
+
+    let mut lottery_state: SecretLotteryState = load_sbi::<SecretLotteryState>(lottery_state_id);
+
+    let purchaser_account_key = lottery_ticket_purchase_secret.purchaser_account_key;
+    let lottery_account_key = lottery_creation_secret.lottery_account_key;
+
+    let mut entered = Sbu1::from(false);
+
+    // Only earlier tickets count, so that queued entries of the same account cannot block each other
+    for variable_id in secret_variable_ids() {
+        if load_metadata::<u8>(variable_id) == VARIABLE_KIND_DISCRIMINANT_LOTTERY_TICKET_PURCHASE
+            && variable_id.raw_id < lottery_ticket_purchase_id.raw_id
+        {
+            let ticket: LotteryTicketPurchaseSecret =
+                load_sbi::<LotteryTicketPurchaseSecret>(variable_id);
+            if ticket.purchaser_account_key == purchaser_account_key
+                && ticket.lottery_account_key == lottery_account_key
+            {
+                entered = Sbu1::from(true);
+            }
+        }
+    }
+
+    let new_tickets = checked_add(
+        lottery_state.tickets,
+        lottery_ticket_purchase_secret.tickets,
+    );
+    let mut successful = Sbu1::from(false);
+
+    if !entered
+        && lottery_ticket_purchase_secret.tickets == Sbu128::from(1)
+        && new_tickets.valid
+        && lottery_ticket_purchase_secret.lottery_account_key == lottery_account_key
+        && purchaser_account_key == purchaser_balance.account_key
+        && has_access_code(lottery_ticket_purchase_secret, lottery_creation_id)
+        && may_enter(
+            purchaser_balance.account_key,
+            lottery_creation_id,
+            allow_creator_entry,
+        )
+    {
+        successful = Sbu1::from(true);
+
+        lottery_state.tickets = new_tickets.value;
+        lottery_state.entropy = lottery_state.entropy + lottery_ticket_purchase_secret.entropy;
+    }
+
+    (
+        lottery_state,
+        ComputationResult {
+            amount: Sbu128::from(0),
+            successful,
         },
     )
 }
 
+/// Computes the statistics of a lottery from its ticket purchases.
+///
+/// Failed purchases are discarded by the contract, so every remaining ticket purchase counts.
+/// Voucher tickets are free entries, so only paid tickets count towards the revenue.
+/// The distinct purchasers are public, so the contract counts them itself.
+/// Returns:
+/// 0: LotteryStatistics -> statistics of the lottery
+#[allow(clippy::too_many_arguments)]
+#[zk_compute(shortname = 0x79)]
+pub fn compute_lottery_statistics(
+    lottery_balance_id: SecretVarId,
+    entry_cost: u128,
+    max_tickets: u128,
+    tier_1_min_tickets: u128,
+    tier_1_price: u128,
+    tier_2_min_tickets: u128,
+    tier_2_price: u128,
+    tier_3_min_tickets: u128,
+    tier_3_price: u128,
+) -> LotteryStatistics {
+    let lottery_balance: AccountBalance = load_sbi::<AccountBalance>(lottery_balance_id);
+    let lottery_account_key = lottery_balance.account_key;
+
+    let mut statistics = LotteryStatistics {
+        tickets: Sbu128::from(0),
+        revenue: Sbu128::from(0),
+        successful: Sbu1::from(true),
+    };
+
+    for variable_id in secret_variable_ids() {
+        let kind = load_metadata::<u8>(variable_id);
+
+        if kind == VARIABLE_KIND_DISCRIMINANT_LOTTERY_TICKET_PURCHASE {
+            let ticket: LotteryTicketPurchaseSecret =
+                load_sbi::<LotteryTicketPurchaseSecret>(variable_id);
+
+            if ticket.lottery_account_key == lottery_account_key {
+                let tickets = checked_add(statistics.tickets, ticket.tickets);
+                statistics.successful = statistics.successful && tickets.valid;
+                statistics.tickets = tickets.value;
+
+                if ticket.voucher_code == Sbu128::from(0) {
+                    let price = discounted_ticket_price(
+                        ticket.tickets,
+                        entry_cost,
+                        tier_1_min_tickets,
+                        tier_1_price,
+                        tier_2_min_tickets,
+                        tier_2_price,
+                        tier_3_min_tickets,
+                        tier_3_price,
+                    );
+                    let cost = checked_mul(ticket.tickets, price, max_tickets);
+                    let revenue = checked_add(statistics.revenue, cost.value);
+                    statistics.successful = statistics.successful && cost.valid && revenue.valid;
+                    statistics.revenue = revenue.value;
+                }
+            }
+        }
+    }
+
+    statistics
+}
+
 /// Claims the winnings for the winner of the lottery.
 /// Returns:
 /// 0: AccountBalance -> Updated winner balance
@@ -450,15 +1353,58 @@ pub fn claim_winnings(
     let mut lottery_balance: AccountBalance = load_sbi::<AccountBalance>(lottery_balance_id);
     let mut winner_balance: AccountBalance = load_sbi::<AccountBalance>(winner_balance_id);
 
-    // Update the winner balance
-    winner_balance.balance = winner_balance.balance + lottery_balance.balance;
+    let new_winner_balance = checked_add(winner_balance.balance, lottery_balance.balance);
 
-    // Reset the lottery balance to zero
-    lottery_balance.balance = Sbu128::from(0);
+    // If the winner balance would overflow, the prize stays in the lottery balance
+    if new_winner_balance.valid {
+        // Update the winner balance
+        winner_balance.balance = new_winner_balance.value;
+
+        // Reset the lottery balance to zero
+        lottery_balance.balance = Sbu128::from(0);
+    }
 
     (winner_balance, lottery_balance)
 }
 
+/// Burns the winnings of a lottery with an anonymous winner, to be paid out in tokens, see
+/// [`draw_lottery_winner`].
+///
+/// Only succeeds if the [`PrizeClaimSecret`] holds the claim secret of the winning ticket
+/// purchase, which only the winner knows. No balance of the winner is involved, so the payout is
+/// never linked to the account of the winner.
+/// Returns:
+/// 0: AccountBalance -> Updated lottery balance
+/// 1: ComputationResult -> whether the claim was successful or not, and the prize to pay out
+#[zk_compute(shortname = 0x7F)]
+pub fn claim_winnings_to_payout(
+    prize_claim_id: SecretVarId,
+    winner_claim_id: SecretVarId,
+    lottery_balance_id: SecretVarId,
+) -> (AccountBalance, ComputationResult) {
+    let claim: PrizeClaimSecret = load_sbi::<PrizeClaimSecret>(prize_claim_id);
+    let winner_claim: PrizeClaimSecret = load_sbi::<PrizeClaimSecret>(winner_claim_id);
+    let mut lottery_balance: AccountBalance = load_sbi::<AccountBalance>(lottery_balance_id);
+
+    let prize = lottery_balance.balance;
+    // Purchases without a claim secret cannot be claimed, as `0` is easily guessed
+    let successful =
+        claim.claim_secret == winner_claim.claim_secret && claim.claim_secret != Sbu128::from(0);
+
+    if successful {
+        // Burn the prize, as it is paid out in tokens
+        lottery_balance.balance = Sbu128::from(0);
+    }
+
+    (
+        lottery_balance,
+        ComputationResult {
+            amount: prize,
+            successful,
+        },
+    )
+}
+
 /// Produces true if the given [`SecretVarId`] points to a [`DepositBalanceSecrets`].
 fn is_account_balance(variable_id: SecretVarId) -> bool {
     let kind = load_metadata::<u8>(variable_id);
@@ -467,11 +1413,19 @@ fn is_account_balance(variable_id: SecretVarId) -> bool {
         return true;
     } else if kind == VARIABLE_KIND_DISCRIMINANT_LOTTERY_ACCOUNT {
         return true;
+    } else if kind == VARIABLE_KIND_DISCRIMINANT_REFERRER_ACCOUNT {
+        return true;
     }
 
     false
 }
 
+/// Produces true if `account_key` is tagged with [`USER_ACCOUNT_KEY_TAG`], rather than being the
+/// account key of a lottery.
+fn is_user_account_key(account_key: AccountKey) -> Sbu1 {
+    is_negative(account_key)
+}
+
 /// Produces true if the given [`Sbu128`] would be negative if casted to [`Sbi128`].
 fn is_negative(x: Sbu128) -> Sbu1 {
     let bits = x.to_le_bits();